@@ -6,13 +6,20 @@ use douyin_sdk::{get_now_timestamp, make_random_string, map_2_str, DouyinConfig,
 async fn main ()  {
     // 它将在编译时写进二进制可执行文件中。如果需要在运行时更新请自行修改。
     let app_private_key = include_str!("private_key.pem"); // 请自行修改私钥，不要使用示例中的
-    let config = DouyinConfig {
+    // 使用 builder 构造，参数有误时返回错误
+    let mut sdk = SDK::builder()
+        .appid("appid")
+        .secret("secret")
+        .app_private_key(app_private_key)
+        .build()
+        .expect("SDK参数错误");
+    // 也可以使用 SDK::new 构造，不做参数校验
+    let _sdk = SDK::new(DouyinConfig {
         appid: "appid",
         secret: "secret",
-        app_private_key: app_private_key,
+        app_private_key,
         ..Default::default()
-    };
-    let mut sdk = SDK::new(config);
+    });
     // 直播小玩法->开发->服务端->接口调用凭证->getAccessToken->获取access_token
     let access_token = sdk.get_access_token().await;
     // 直播小玩法->开发->服务端->直播能力->数据开放->启动任务
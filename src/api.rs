@@ -0,0 +1,218 @@
+use std::future::Future;
+
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+
+use crate::{telemetry, DouyinError, SDK};
+
+/**
+ * 接口返回的错误码和错误信息，call_api 用来判断 token 是否失效、记录埋点和转换错误
+ */
+pub(crate) trait ApiResult {
+    fn code(&self) -> i64;
+    fn message(&self) -> String;
+    fn logid(&self) -> &str {
+        ""
+    }
+
+    /**
+     * 错误码不为0时转换为 DouyinError::Api
+     */
+    fn into_result(self) -> Result<Self, DouyinError> where Self: Sized {
+        if self.code() != 0 {
+            return Err(DouyinError::Api { code: self.code(), msg: self.message() });
+        }
+        Ok(self)
+    }
+}
+
+/**
+ * 只有 err_no/err_tips 的返回结构体
+ */
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub(crate) struct BaseRes {
+    #[serde(alias = "errcode")]
+    pub err_no: i64,
+    #[serde(alias = "errmsg", alias = "err_msg")]
+    pub err_tips: String,
+    pub log_id: String,
+}
+
+impl ApiResult for BaseRes {
+    fn code(&self) -> i64 {
+        self.err_no
+    }
+    fn message(&self) -> String {
+        self.err_tips.clone()
+    }
+    fn logid(&self) -> &str {
+        &self.log_id
+    }
+}
+
+/**
+ * err_no/err_tips 和数据在同一层的返回结构体，data 为接口自己的字段
+ */
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, bound(deserialize = "T: DeserializeOwned + Default"))]
+pub(crate) struct ApiRes<T> {
+    #[serde(flatten)]
+    pub base: BaseRes,
+    #[serde(flatten)]
+    pub data: T,
+}
+
+impl<T> ApiResult for ApiRes<T> {
+    fn code(&self) -> i64 {
+        self.base.code()
+    }
+    fn message(&self) -> String {
+        self.base.message()
+    }
+    fn logid(&self) -> &str {
+        self.base.logid()
+    }
+}
+
+/**
+ * 需要 access_token 的接口的请求方式
+ */
+pub(crate) enum Endpoint<'a> {
+    // access_base_url 下的接口，access_token 放在 X-Token 请求头；字段名不为空时同时放到 body 的该字段中（部分老接口要求）
+    Developer(Option<&'a str>),
+    // base_url 下的接口，access_token 放在 X-Token 请求头
+    Access,
+    // 参数为 base_url，使用 Byte-Authorization 签名
+    Signed(&'a str),
+}
+
+/**
+ * 把返回的 json 解析为 T
+ */
+pub(crate) async fn json<T: DeserializeOwned>(response: reqwest::Response) -> Result<T, DouyinError> {
+    Ok(response.json::<T>().await?)
+}
+
+/**
+ * call_binary 的返回，接口返回 json 格式的错误信息时 image 为空
+ */
+struct BinaryRes {
+    image: Option<(String, Vec<u8>)>,
+    error: BaseRes,
+}
+
+impl ApiResult for BinaryRes {
+    fn code(&self) -> i64 {
+        self.error.code()
+    }
+    fn message(&self) -> String {
+        self.error.message()
+    }
+}
+
+/**
+ * 接口成功时返回图片；返回 json 格式的错误信息时交给调用方判断是否需要重试；
+ * http 状态码不是 2xx 时返回 DouyinError::Http，状态码正常但 content-type 不是 image/ 开头时（例如网关的错误页面）也返回错误
+ */
+async fn binary(response: reqwest::Response) -> Result<BinaryRes, DouyinError> {
+    let status_err = response.error_for_status_ref().err();
+    let content_type = response.headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let bytes = response.bytes().await?;
+    if status_err.is_none() && content_type.starts_with("image/") {
+        return Ok(BinaryRes { image: Some((content_type, bytes.to_vec())), error: BaseRes::default() });
+    }
+    if let Ok(error) = serde_json::from_slice::<BaseRes>(&bytes) {
+        if error.code() != 0 {
+            return Ok(BinaryRes { image: None, error });
+        }
+    }
+    if let Some(err) = status_err {
+        return Err(err.into());
+    }
+    Err(DouyinError::Api { code: -1, msg: format!("接口返回的不是图片, content-type: {}", content_type) })
+}
+
+impl SDK {
+    /**
+     * 调用 access_base_url 下需要 access_token 的接口，access_token 放在 X-Token 请求头
+     * token_field 不为空时，同时把 access_token 放到 body 的该字段中（部分老接口要求）
+     * token 失效时清除缓存后重试一次，错误码不为0时返回 DouyinError::Api
+     */
+    pub(crate) async fn call_api<T>(&mut self, path: &str, body: Value, token_field: Option<&str>) -> Result<T, DouyinError>
+        where T: DeserializeOwned + ApiResult
+    {
+        let span = telemetry::CallSpan::call(path);
+        self.retry_on_token_error(span, Endpoint::Developer(token_field), path, &body, json::<T>).await?.into_result()
+    }
+
+    /**
+     * 与 call_api 相同，但接口成功时返回图片，返回 (content_type, 内容)
+     * 接口返回 json 格式的错误信息时转换为 DouyinError::Api；http 状态码不是 2xx 时返回 DouyinError::Http，
+     * 状态码正常但 content-type 不是 image/ 开头时（例如网关的错误页面）也返回错误，不会把它当作图片
+     */
+    pub(crate) async fn call_binary(&mut self, path: &str, body: Value, token_field: Option<&str>) -> Result<(String, Vec<u8>), DouyinError> {
+        let span = telemetry::CallSpan::call(path);
+        let data = self.retry_on_token_error(span, Endpoint::Developer(token_field), path, &body, binary).await?.into_result()?;
+        data.image.ok_or_else(|| DouyinError::Api { code: -1, msg: "接口没有返回图片".into() })
+    }
+
+    /**
+     * 在 span 内请求需要 access_token 的接口并用 parse 解析返回
+     * 返回 token 无效的错误码时作废 token 后重试一次；不检查错误码，由调用方处理
+     */
+    pub(crate) async fn retry_on_token_error<T, P, F>(&mut self, span: telemetry::CallSpan, endpoint: Endpoint<'_>, path: &str, body: &Value, parse: P) -> Result<T, DouyinError>
+        where T: ApiResult, P: Fn(reqwest::Response) -> F, F: Future<Output = Result<T, DouyinError>>
+    {
+        span.trace(async {
+            let data = parse(self.send_api(&endpoint, path, body).await?).await?;
+            // token被提前吊销时，清除缓存后重试一次
+            if self.invalidate_if_token_error(data.code()).await {
+                return parse(self.send_api(&endpoint, path, body).await?).await;
+            }
+            Ok(data)
+        }).await
+    }
+
+    async fn send_api(&mut self, endpoint: &Endpoint<'_>, path: &str, body: &Value) -> Result<reqwest::Response, DouyinError> {
+        match endpoint {
+            Endpoint::Developer(token_field) => {
+                let mut body = body.clone();
+                if let Some(field) = token_field {
+                    body[*field] = json!(self.get_access_token().await?);
+                }
+                self.developer_request(path, body).await
+            },
+            Endpoint::Access => self.access_request(path, body.clone()).await,
+            Endpoint::Signed(base_url) => self.sign_request_with(reqwest::Method::POST, base_url, path, &[], Some(body.clone())).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize, Debug, Default)]
+    #[serde(default)]
+    struct Data {
+        refund_no: String,
+    }
+
+    #[test]
+    fn api_res_reads_base_fields_next_to_data() {
+        let res: ApiRes<Data> = serde_json::from_str(r#"{"err_no":0,"err_tips":"","refund_no":"r1"}"#).unwrap();
+        assert_eq!(res.into_result().unwrap().data.refund_no, "r1");
+
+        let res: ApiRes<Data> = serde_json::from_str(r#"{"err_no":40002,"err_msg":"token无效","log_id":"l1"}"#).unwrap();
+        assert_eq!(res.logid(), "l1");
+        assert!(matches!(res.into_result(), Err(DouyinError::Api { code: 40002, msg }) if msg == "token无效"));
+
+        let res: ApiRes<Data> = serde_json::from_str(r#"{"errcode":40004,"errmsg":"过期"}"#).unwrap();
+        assert_eq!(res.code(), 40004);
+    }
+}
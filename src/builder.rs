@@ -0,0 +1,310 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{secret::SecretKey, sign, DouyinError, FileTokenStore, MemoryUserTokenStore, RateLimiter, Secret, TokenStore, UserTokenStore, SDK, TOKEN_INVALID_CODES};
+
+pub(crate) const DEFAULT_BASE_URL: &str = "https://webcast.bytedance.com";
+pub(crate) const DEFAULT_ACCESS_BASE_URL: &str = "https://developer.toutiao.com";
+pub(crate) const DEFAULT_OPEN_BASE_URL: &str = "https://open.douyin.com";
+pub(crate) const DEFAULT_CACHE_FILE_NAME: &str = "douyin_access_token.json";
+pub(crate) const DEFAULT_TOKEN_SAFETY_MARGIN: u64 = 600;
+
+/**
+ * 请求的重试策略
+ * 默认不重试，只有连接失败或超时这类网络错误才会重试，接口返回的错误码不会重试。
+ */
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32, // 最大重试次数，0为不重试
+    pub backoff: Duration, // 每次重试前等待的时间，第n次重试等待 n * backoff
+    pub token_fallback_secs: u64, // 获取access_token失败后，间隔多少秒再重新请求
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 0,
+            backoff: Duration::from_millis(200),
+            token_fallback_secs: 60,
+        }
+    }
+}
+
+/**
+ * SDK 的构造器，通过 SDK::builder() 获取
+ * 与 SDK::new 不同，build 时会校验参数并提前解析私钥，参数有误时返回错误而不是在第一次请求时panic
+ */
+#[derive(Debug, Default)]
+pub struct SdkBuilder {
+    appid: String,
+    secret: Secret,
+    app_private_key: Option<Secret>,
+    pkcs_type: Option<sign::PkcsType>,
+    base_url: Option<String>,
+    access_base_url: Option<String>,
+    open_base_url: Option<String>,
+    access_token_cache_file_path: Option<String>,
+    token_store: Option<Arc<dyn TokenStore>>,
+    user_token_store: Option<Arc<dyn UserTokenStore>>,
+    client: Option<reqwest::Client>,
+    retry_policy: RetryPolicy,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    token_safety_margin: Option<Duration>,
+    token_invalid_codes: Option<Vec<i64>>,
+    pay_salt: Secret,
+    pay_token: Secret,
+    pay_callback_max_age: Option<Duration>,
+}
+
+impl SdkBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn appid(mut self, appid: &str) -> Self {
+        self.appid = appid.to_string();
+        self
+    }
+
+    pub fn secret(mut self, secret: &str) -> Self {
+        self.secret = Secret::from(secret);
+        self
+    }
+
+    /**
+     * app的私钥，可以是PEM格式，也可以是单行的base64字符串
+     */
+    pub fn app_private_key(mut self, app_private_key: &str) -> Self {
+        self.app_private_key = Some(Secret::from(app_private_key));
+        self
+    }
+
+    /**
+     * 私钥的格式，不设置时根据PEM头自动判断
+     */
+    pub fn pkcs_type(mut self, pkcs_type: sign::PkcsType) -> Self {
+        self.pkcs_type = Some(pkcs_type);
+        self
+    }
+
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        self.base_url = Some(base_url.to_string());
+        self
+    }
+
+    pub fn access_base_url(mut self, access_base_url: &str) -> Self {
+        self.access_base_url = Some(access_base_url.to_string());
+        self
+    }
+
+    /**
+     * 交易系统等 open.douyin.com 下接口的地址
+     */
+    pub fn open_base_url(mut self, open_base_url: &str) -> Self {
+        self.open_base_url = Some(open_base_url.to_string());
+        self
+    }
+
+    /**
+     * access_token缓存文件路径，设置了 token_store 时不生效
+     */
+    pub fn access_token_cache_file_path(mut self, path: &str) -> Self {
+        self.access_token_cache_file_path = Some(path.to_string());
+        self
+    }
+
+    /**
+     * 自定义access_token缓存，默认为文件缓存
+     */
+    pub fn token_store(mut self, token_store: Arc<dyn TokenStore>) -> Self {
+        self.token_store = Some(token_store);
+        self
+    }
+
+    /**
+     * 用户授权 token 的存储，默认保存在内存中
+     */
+    pub fn user_token_store(mut self, user_token_store: Arc<dyn UserTokenStore>) -> Self {
+        self.user_token_store = Some(user_token_store);
+        self
+    }
+
+    /**
+     * 自定义http客户端，可以在这里设置超时、代理等
+     */
+    pub fn client(mut self, client: reqwest::Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /**
+     * 客户端限流，传入 Arc 时可以在多个 SDK 之间共享
+     */
+    pub fn rate_limiter(mut self, rate_limiter: impl Into<Arc<RateLimiter>>) -> Self {
+        self.rate_limiter = Some(rate_limiter.into());
+        self
+    }
+
+    /**
+     * access_token 在过期前多久开始刷新，默认600秒
+     * 大于等于接口返回的有效期时，在有效期过半时刷新
+     */
+    pub fn token_safety_margin(mut self, margin: Duration) -> Self {
+        self.token_safety_margin = Some(margin);
+        self
+    }
+
+    /**
+     * 表示 access_token 失效的错误码，默认为 TOKEN_INVALID_CODES
+     */
+    pub fn token_invalid_codes(mut self, codes: &[i64]) -> Self {
+        self.token_invalid_codes = Some(codes.to_vec());
+        self
+    }
+
+    /**
+     * 担保支付的签名salt，在开发者后台->支付->担保支付设置中获取
+     */
+    pub fn pay_salt(mut self, salt: &str) -> Self {
+        self.pay_salt = Secret::from(salt);
+        self
+    }
+
+    /**
+     * 担保支付回调验签的token，与开发者后台->支付->担保支付设置中填写的一致
+     */
+    pub fn pay_token(mut self, token: &str) -> Self {
+        self.pay_token = Secret::from(token);
+        self
+    }
+
+    /**
+     * 担保支付回调的 timestamp 与当前时间最多相差多久，超过时 verify_pay_callback 拒绝该回调，避免回调被重放
+     * 默认不校验
+     */
+    pub fn pay_callback_max_age(mut self, max_age: Duration) -> Self {
+        self.pay_callback_max_age = Some(max_age);
+        self
+    }
+
+    /**
+     * 校验参数并构造 SDK
+     */
+    pub fn build(self) -> Result<SDK, DouyinError> {
+        if self.appid.trim().is_empty() {
+            return Err(DouyinError::Config("appid不能为空".into()));
+        }
+        if self.secret.expose().trim().is_empty() {
+            return Err(DouyinError::Config("secret不能为空".into()));
+        }
+        let base_url = check_url("base_url", self.base_url.as_deref().unwrap_or(DEFAULT_BASE_URL))?;
+        let access_base_url = check_url("access_base_url", self.access_base_url.as_deref().unwrap_or(DEFAULT_ACCESS_BASE_URL))?;
+        let open_base_url = check_url("open_base_url", self.open_base_url.as_deref().unwrap_or(DEFAULT_OPEN_BASE_URL))?;
+
+        let (private_key, pkcs_type) = match &self.app_private_key {
+            Some(key) => {
+                let (private_key, pkcs_type) = sign::parse_pri_key(key.expose(), self.pkcs_type)
+                    .map_err(DouyinError::PrivateKey)?;
+                (Some(SecretKey(private_key)), pkcs_type)
+            },
+            None => (None, self.pkcs_type.unwrap_or(sign::PkcsType::Pkcs8)),
+        };
+
+        let access_token_cache_file_path = match self.access_token_cache_file_path {
+            Some(path) => path,
+            None => SDK::get_exe_path(DEFAULT_CACHE_FILE_NAME)?.to_string_lossy().into_owned(),
+        };
+        let token_store = self.token_store
+            .unwrap_or_else(|| Arc::new(FileTokenStore::new(&access_token_cache_file_path)));
+
+        Ok(SDK {
+            appid: self.appid,
+            secret: self.secret,
+            app_private_key: self.app_private_key.unwrap_or_default(),
+            pkcs_type,
+            base_url,
+            access_base_url,
+            open_base_url,
+            access_token_cache_file_path,
+            access_token: Secret::default(),
+            expires_in: 0,
+            private_key,
+            client: self.client.unwrap_or_default(),
+            token_store,
+            retry_policy: self.retry_policy,
+            rate_limiter: self.rate_limiter,
+            token_rx: None,
+            token: None,
+            expires_at: None,
+            clock_anchor: None,
+            token_safety_margin: self.token_safety_margin.map_or(DEFAULT_TOKEN_SAFETY_MARGIN, |margin| margin.as_secs()),
+            token_invalid_codes: self.token_invalid_codes.unwrap_or_else(|| TOKEN_INVALID_CODES.to_vec()),
+            revoked_token: None,
+            pay_salt: self.pay_salt,
+            pay_token: self.pay_token,
+            pay_callback_max_age: self.pay_callback_max_age,
+            user_token_store: self.user_token_store.unwrap_or_else(|| Arc::new(MemoryUserTokenStore::new())),
+        })
+    }
+}
+
+/**
+ * 校验url是否合法，并去掉末尾的 `/`，避免和接口路径拼接出 `//`
+ */
+fn check_url(name: &str, url: &str) -> Result<String, DouyinError> {
+    let parsed = reqwest::Url::parse(url)
+        .map_err(|err| DouyinError::Config(format!("{}不是合法的url: {}, {}", name, url, err)))?;
+    if parsed.scheme() != "http" && parsed.scheme() != "https" {
+        return Err(DouyinError::Config(format!("{}只支持http/https: {}", name, url)));
+    }
+    Ok(url.trim_end_matches('/').to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builder() -> SdkBuilder {
+        SDK::builder().appid("appid").secret("secret").token_store(Arc::new(crate::MemoryTokenStore::new()))
+    }
+
+    fn config_error(builder: SdkBuilder) -> String {
+        match builder.build() {
+            Err(DouyinError::Config(msg)) => msg,
+            other => panic!("应返回配置错误: {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn appid_and_secret_are_required() {
+        assert!(config_error(SDK::builder().secret("secret")).contains("appid"));
+        assert!(config_error(builder().appid("  ")).contains("appid"));
+        assert!(config_error(SDK::builder().appid("appid")).contains("secret"));
+        assert!(config_error(builder().secret(" \t")).contains("secret"));
+    }
+
+    #[test]
+    fn urls_are_checked_and_trimmed() {
+        assert!(config_error(builder().base_url("ftp://example.com")).contains("http/https"));
+        assert!(config_error(builder().access_base_url("example.com")).contains("access_base_url"));
+        assert!(config_error(builder().open_base_url("http://")).contains("open_base_url"));
+
+        let sdk = builder().base_url("http://127.0.0.1:8080/").open_base_url("https://example.com//").build().unwrap();
+        assert_eq!(sdk.base_url, "http://127.0.0.1:8080");
+        assert_eq!(sdk.open_base_url, "https://example.com");
+        assert_eq!(sdk.access_base_url, DEFAULT_ACCESS_BASE_URL);
+    }
+
+    #[test]
+    fn private_key_is_parsed_when_building() {
+        assert!(matches!(builder().app_private_key("not a key").build(), Err(DouyinError::PrivateKey(_))));
+
+        let sdk = builder().app_private_key(include_str!("../examples/private_key.pem")).build().unwrap();
+        assert!(sdk.private_key.is_some());
+    }
+}
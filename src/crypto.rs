@@ -0,0 +1,170 @@
+use aes::cipher::{block_padding::Pkcs7, BlockDecryptMut, KeyIvInit};
+use base64::Engine;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+
+use crate::{sign, DouyinError};
+
+type Aes128CbcDec = cbc::Decryptor<aes::Aes128>;
+
+/**
+ * 敏感数据中的水印，用来校验数据是否属于当前小程序
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct Watermark {
+    pub appid: String,
+    pub timestamp: u64,
+}
+
+impl Watermark {
+    /**
+     * 校验水印中的 appid
+     */
+    pub fn check(&self, appid: &str) -> Result<(), DouyinError> {
+        if self.appid != appid {
+            return Err(DouyinError::Decrypt(format!("水印appid不匹配: {}", self.appid)));
+        }
+        Ok(())
+    }
+}
+
+/**
+ * tt.getPhoneNumber 解密后的手机号
+ */
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PhoneNumber {
+    pub phone_number: String, // 带区号的手机号
+    pub pure_phone_number: String, // 不带区号的手机号
+    pub country_code: String, // 区号
+    pub watermark: Watermark,
+}
+
+/**
+ * tt.getUserInfo 解密后的用户信息
+ */
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UserInfo {
+    #[serde(default)]
+    pub open_id: String,
+    #[serde(default)]
+    pub union_id: String,
+    pub nick_name: String,
+    pub avatar_url: String,
+    #[serde(default)]
+    pub gender: u8, // 0 未知 1 男 2 女
+    #[serde(default)]
+    pub city: String,
+    #[serde(default)]
+    pub province: String,
+    #[serde(default)]
+    pub country: String,
+    #[serde(default)]
+    pub language: String,
+    pub watermark: Watermark,
+}
+
+/**
+ * 解密小程序传来的 encryptedData，算法为 AES-128-CBC，PKCS#7 填充
+ * encrypted_data、session_key、iv 都是 base64 编码，返回解密后的 json 字符串
+ */
+pub fn decrypt(encrypted_data: &str, session_key: &str, iv: &str) -> Result<String, DouyinError> {
+    let engine = base64::engine::general_purpose::STANDARD;
+    let decode = |name: &str, value: &str| engine.decode(value)
+        .map_err(|err| DouyinError::Decrypt(format!("{} base64解码失败: {}", name, err)));
+    let data = decode("encryptedData", encrypted_data)?;
+    let key = decode("session_key", session_key)?;
+    let iv = decode("iv", iv)?;
+    let decryptor = Aes128CbcDec::new_from_slices(&key, &iv)
+        .map_err(|_| DouyinError::Decrypt("session_key或iv长度错误".into()))?;
+    let plain = decryptor.decrypt_padded_vec_mut::<Pkcs7>(&data)
+        .map_err(|_| DouyinError::Decrypt("解密失败，请检查session_key是否已过期".into()))?;
+    String::from_utf8(plain).map_err(|err| DouyinError::Decrypt(err.to_string()))
+}
+
+/**
+ * 解密并反序列化为指定的结构体
+ */
+pub fn decrypt_data<T: DeserializeOwned>(encrypted_data: &str, session_key: &str, iv: &str) -> Result<T, DouyinError> {
+    let json_str = decrypt(encrypted_data, session_key, iv)?;
+    Ok(serde_json::from_str(&json_str)?)
+}
+
+/**
+ * 解密手机号
+ */
+pub fn decrypt_phone_number(encrypted_data: &str, session_key: &str, iv: &str) -> Result<PhoneNumber, DouyinError> {
+    decrypt_data(encrypted_data, session_key, iv)
+}
+
+/**
+ * 解密用户信息
+ */
+pub fn decrypt_user_info(encrypted_data: &str, session_key: &str, iv: &str) -> Result<UserInfo, DouyinError> {
+    decrypt_data(encrypted_data, session_key, iv)
+}
+
+/**
+ * 校验 tt.getUserInfo 返回的 rawData 签名，signature = sha1(rawData + session_key)
+ */
+pub fn check_signature(raw_data: &str, session_key: &str, signature: &str) -> bool {
+    let hashed = Sha1::new()
+        .chain_update(raw_data.as_bytes())
+        .chain_update(session_key.as_bytes())
+        .finalize();
+    sign::constant_time_eq(&format!("{:x}", hashed), &signature.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aes::cipher::BlockEncryptMut;
+
+    // base64("0123456789abcdef") 和 base64("fedcba9876543210")
+    const KEY: &str = "MDEyMzQ1Njc4OWFiY2RlZg==";
+    const IV: &str = "ZmVkY2JhOTg3NjU0MzIxMA==";
+    // openssl enc -aes-128-cbc 加密的手机号 json
+    const PHONE: &str = "/zKksLWsmvxbw65eoGVnlH4y6xpGY6+4/quDq2+KMEXpsyZQvBULcl7FC38bdCokJBPRupeydswspPTm7TvjlfdIRMXMBpztmjUgdQf7i2WFWFMGSjOpHJevvdRNPL78XSiXM9H875aSCr/808Rp+PH5fkYgPaDJV2tJO0tRgPf4VBH+4NSoR8zE/ekZcBHm";
+
+    #[test]
+    fn decrypt_phone_number_with_fixed_key() {
+        let phone = decrypt_phone_number(PHONE, KEY, IV).unwrap();
+        assert_eq!(phone.pure_phone_number, "13800000000");
+        assert_eq!(phone.country_code, "86");
+        assert!(phone.watermark.check("tt123").is_ok());
+        assert!(matches!(phone.watermark.check("tt456"), Err(DouyinError::Decrypt(_))));
+    }
+
+    #[test]
+    fn decrypt_round_trip() {
+        let engine = base64::engine::general_purpose::STANDARD;
+        let plain = r#"{"nickName":"抖音用户","avatarUrl":"https://example.com/a.png","watermark":{"appid":"tt123","timestamp":1}}"#;
+        let encrypted = cbc::Encryptor::<aes::Aes128>::new_from_slices(b"0123456789abcdef", b"fedcba9876543210")
+            .unwrap()
+            .encrypt_padded_vec_mut::<Pkcs7>(plain.as_bytes());
+        let encrypted = engine.encode(encrypted);
+        assert_eq!(decrypt(&encrypted, KEY, IV).unwrap(), plain);
+        assert_eq!(decrypt_user_info(&encrypted, KEY, IV).unwrap().nick_name, "抖音用户");
+    }
+
+    #[test]
+    fn decrypt_errors() {
+        // 最后一个字节不是合法的 PKCS#7 填充
+        let bad_padding = "FQq7MLO4Z7s8v2zJXAEqOw==";
+        assert!(matches!(decrypt(bad_padding, KEY, IV), Err(DouyinError::Decrypt(msg)) if msg.contains("session_key")));
+        // session_key 只有8字节
+        assert!(matches!(decrypt(PHONE, "MDEyMzQ1Njc=", IV), Err(DouyinError::Decrypt(msg)) if msg.contains("长度")));
+        assert!(matches!(decrypt(PHONE, KEY, "MDEyMzQ1Njc="), Err(DouyinError::Decrypt(msg)) if msg.contains("长度")));
+        assert!(matches!(decrypt("不是base64", KEY, IV), Err(DouyinError::Decrypt(msg)) if msg.contains("encryptedData")));
+    }
+
+    #[test]
+    fn check_signature_ignores_case() {
+        let signature = "e6b3add3c1f88fa879cb291577154edf314d5de2";
+        assert!(check_signature(r#"{"nickName":"a"}"#, "sk", signature));
+        assert!(check_signature(r#"{"nickName":"a"}"#, "sk", &signature.to_uppercase()));
+        assert!(!check_signature(r#"{"nickName":"b"}"#, "sk", signature));
+        assert!(!check_signature(r#"{"nickName":"a"}"#, "sk", ""));
+    }
+}
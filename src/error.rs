@@ -0,0 +1,51 @@
+/**
+ * SDK 统一的错误类型
+ */
+#[derive(Debug, thiserror::Error)]
+pub enum DouyinError {
+    /// 构造 SDK 时的参数错误，例如 appid 为空、url 不合法
+    #[error("配置错误: {0}")]
+    Config(String),
+    /// 调用接口时传入的参数不合法
+    #[error("参数错误: {0}")]
+    InvalidParam(String),
+    /// 用户没有授权，或授权已过期需要重新授权
+    #[error("用户未授权或授权已过期: {0}")]
+    Unauthorized(String),
+    /// 私钥解析失败
+    #[error("私钥解析失败: {0}")]
+    PrivateKey(String),
+    /// 网络请求失败
+    #[error("请求失败: {0}")]
+    Http(#[from] reqwest::Error),
+    /// 抖音平台返回的错误码
+    #[error("接口返回错误,err_no:{code}, err_msg: {msg}")]
+    Api { code: i64, msg: String },
+    /// json 序列化/反序列化失败
+    #[error("json解析失败: {0}")]
+    Json(#[from] serde_json::Error),
+    /// SdkRegistry 中没有注册该 appid，或直播间没有绑定 appid
+    #[error("未注册的应用: {0}")]
+    UnknownApp(String),
+    /// 用户敏感数据解密失败
+    #[error("解密失败: {0}")]
+    Decrypt(String),
+    /// 超出客户端限流配置
+    #[error("请求过于频繁: {0}")]
+    RateLimited(String),
+    /// 作废 access_token 后重新获取，平台返回的仍是刚作废的 token
+    #[error("平台重新下发了已作废的access_token")]
+    RevokedTokenReissued,
+    /// 没有可用的 access_token，例如获取失败后等待重新获取期间
+    #[error("没有可用的access_token，等待重新获取")]
+    TokenUnavailable,
+    /// 回调签名校验失败
+    #[error("签名校验失败")]
+    SignMismatch,
+    /// 回调的 timestamp 超出允许的时间范围，可能是重放的请求
+    #[error("回调已过期: {0}")]
+    CallbackExpired(String),
+    /// 文件读写失败
+    #[error("文件读写失败: {0}")]
+    Io(#[from] std::io::Error),
+}
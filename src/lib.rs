@@ -8,7 +8,7 @@
 //! 
 //! 对应的 抖音官方文档为[https://developer.open-douyin.com/docs/resource/zh-CN/interaction/develop/server/server-api-introduction]()
 //! 
-//! 我刚看到抖音的服务端分为很多版块，这里只是直播小玩法的服务端文档，由于对文档不熟悉，不知道如何抽取公共功能。针对版本号~0.1，仅对其扩展不做破坏性变更
+//! 我刚看到抖音的服务端分为很多版块，这里只是直播小玩法的服务端文档，由于对文档不熟悉，不知道如何抽取公共功能。针对版本号~0.1，仅对其扩展不做破坏性变更；0.2 为了把 panic 改为返回错误修改了部分接口，升级说明见 CHANGELOG.md
 //! 
 //! 这只是一个练手项目，对于rust我还有很多困惑，也不知道如何精简项目，欢迎对Rust进行学习和交流。
 //! 
@@ -26,11 +26,13 @@
 //! rsa={version="0.9", features = ["sha2"]}
 //! base64="0.22"
 //! ```
+//!
+//! 最低支持的 Rust 版本（MSRV）为 1.82，见 Cargo.toml 中的 `rust-version`。
 //! 
 //! 
 //! # Example
 //! 
-//! ```rust
+//! ```rust,ignore
 //!     let app_private_key = include_str!("private_key.pem");
 //!     let config = DouyinConfig {
 //!         appid: "appid",
@@ -40,6 +42,13 @@
 //!     };
 //!     let mut sdk = SDK::new(config);
 //! 
+//!     // 或者使用 builder 构造，会校验参数并提前解析私钥，参数有误时返回 DouyinError
+//!     let mut sdk = SDK::builder()
+//!         .appid("appid")
+//!         .secret("secret")
+//!         .app_private_key(app_private_key)
+//!         .build()?;
+//! 
 //!     // 直播小玩法->开发->服务端->接口调用凭证->getAccessToken->获取access_token
 //!     let token = sdk.get_access_token().await;
 //!     // 直播小玩法->开发->服务端->直播能力->数据开放->启动任务
@@ -50,41 +59,209 @@
 //!     let status_res = sdk.task::<LiveOpenReqDataStatus>("status","roomid","appid","msg_type").await;
 //!     // 直播小玩法->开发->服务端->直播能力->直播信息
 //!     let info = sdk.info("exe启动时携带的token").await;
+//!     // 小程序->服务端->登录->code2Session
+//!     let session = sdk.code2session(Some("tt.login获取的code"), None).await;
+//!     // 小程序->服务端->订阅消息->发送订阅消息
+//!     let message = SubscribeMessage::builder("tpl_id","open_id").field("thing1","对局结束").page("pages/index").build()?;
+//!     sdk.send_subscribe_message(&message).await?;
+//!     // 小程序->服务端->二维码/schema/链接
+//!     let qrcode = sdk.create_qrcode(&QrCodeOptions { appname: Some("douyin".into()), ..Default::default() }).await?;
+//!     let link = sdk.generate_url_link("douyin", "pages/index", None, LinkExpire::After(Duration::from_secs(86400))).await?;
 //!
 //!     let random_str = make_random_string();
 //!     let ts = get_now_timestamp(false);
 //! ````
+//!
+//! # 多应用
+//!
+//! ```rust,ignore
+//!     // 一个进程内管理多个 appid，共享 http 客户端和 access_token 缓存
+//!     let registry = SdkRegistry::new();
+//!     registry.register(AppCredentials {
+//!         appid: "appid".to_string(),
+//!         secret: "secret".into(),
+//!         app_private_key: Some(app_private_key.into()),
+//!         ..Default::default()
+//!     }).await;
+//!     // 启动任务成功后会记录直播间和 appid 的对应关系
+//!     let start_res = registry.task::<LiveOpenReqDataStart>("appid","start","roomid","msg_type").await;
+//!     // 推送回调按直播间分发到对应的应用，并校验签名
+//!     let appid = registry.route_push(sign_map, &body, "x-signature")?;
+//! ```
+//!
+//! # 可选 feature
+//!
+//! - `tracing`: 使用 [tracing](https://docs.rs/tracing) 输出每次接口调用的 span（path、roomid、msg_type、logid、耗时、重试次数、结果）和 access_token 刷新、缓存读写事件。未开启时 SDK 不会向标准输出打印任何内容。
+//! - `metrics`: 通过 [metrics](https://docs.rs/metrics) 门面输出指标，需要自行安装 exporter：
+//!   - `douyin_calls_total` / `douyin_call_duration_seconds`: 按接口(path)统计调用次数、结果和耗时
+//!   - `douyin_api_errors_total`: 按接口和 err_no 统计平台返回的错误码
+//!   - `douyin_token_refresh_total`: access_token 刷新成功/失败次数
+//!   - `douyin_token_cache_total`: access_token 缓存命中/未命中次数
+//!
+//! ```toml
+//! douyin_sdk = { version = "0.2", features = ["tracing", "metrics"] }
+//! ```
+//!
+//! # 担保支付
+//!
+//! ```rust,ignore
+//!     // 通过 builder 设置支付 salt
+//!     let sdk = SDK::builder().appid("appid").secret("secret").pay_salt("salt").build()?;
+//!     // 金额使用 Fen 类型，元需要显式转换，避免单位混用
+//!     let amount: Fen = "9.90".parse::<Yuan>()?.into();
+//!     let order = sdk.create_order(&CreateOrder::new("out_order_no", amount, "商品", "商品详情", 900)).await?;
+//!     let status = sdk.query_order("out_order_no", None).await?.payment_info.order_status;
+//!     // 退款和分账，单号相同的重复请求不会重复退款/分账；订单号、金额一致且没有失败时返回 duplicated 为 true 的结果
+//!     let refund = sdk.create_refund(&CreateRefund::new("out_order_no", "out_refund_no", amount, "退款原因")).await?;
+//!     let settle = sdk.create_settle(&CreateSettle::new("out_order_no", "out_settle_no", "分账")).await?;
+//!     // 支付回调，需要通过 builder 的 pay_token 设置回调token，可以通过 pay_callback_max_age 拒绝过期（重放）的回调
+//!     let response = match sdk.verify_pay_callback(&body) {
+//!         Ok(Notification::Payment(payment)) => callback::success_response(),
+//!         Ok(_) => callback::success_response(),
+//!         Err(err) => callback::failure_response(&err.to_string()),
+//!     };
+//! ```
+//!
+//! # 交易系统
+//!
+//! ```rust,ignore
+//!     // 交易系统的接口使用 Byte-Authorization 签名，需要设置 app_private_key
+//!     let sku = TradeSku { sku_id: "sku".into(), price: Fen(990), quantity: 1, title: "商品".into(), ..Default::default() };
+//!     let schema = OrderEntrySchema { path: "pages/order".into(), params: "{}".into() };
+//!     let order = sdk.create_trade_order(&CreateTradeOrder::new("out_order_no", vec![sku], schema)?).await?;
+//!     let info = sdk.query_trade_order(None, Some("out_order_no")).await?;
+//!     sdk.push_trade_delivery("out_order_no", &[]).await?;
+//!     // 自定义签名请求，支持 GET 和 query 参数
+//!     let res = sdk.sign_request_with(reqwest::Method::GET, &sdk.open_base_url.clone(), "/path", &[("key", "value")], None).await?;
+//!     // 更底层的签名请求，签名和发送的是同一份 body 字节
+//!     let request = SignedRequest::new(reqwest::Method::POST, "/path?key=value").base_url(&sdk.open_base_url).body(r#"{"a":1}"#).content_type("application/json");
+//!     let res = sdk.send_signed(request).await?;
+//! ```
+//!
+//! # 用户授权
+//!
+//! ```rust,ignore
+//!     // 抖音开放平台的用户授权，client_key 使用 appid，用户 token 默认保存在内存中，可以通过 builder 的 user_token_store 自定义
+//!     let url = sdk.oauth_authorize_url(&["user_info", "fans.list"], "https://example.com/callback", "state")?;
+//!     // 授权回调中拿到 code 后换取 token
+//!     let token = sdk.oauth_access_token("code").await?;
+//!     // 之后通过 open_id 获取可用的 access_token，快过期时会自动刷新
+//!     let access_token = sdk.user_access_token(&token.open_id).await?;
+//!     // refresh_token 过期前续期
+//!     sdk.oauth_renew_refresh_token(&token.open_id).await?;
+//!     // 用户信息和粉丝数据
+//!     let user = sdk.user_info(&token.open_id).await?;
+//!     let fans_count = sdk.fans_count(&token.open_id).await?;
+//!     let page = sdk.fans_list(&token.open_id, 0, 20).await?;
+//!     // 列表接口按游标自动翻页，返回 futures::Stream，可以设置每页数量和最多返回的数量
+//!     let fans: Vec<Fan> = sdk.fans(&token.open_id).max_items(100).try_collect().await?;
+//!     let mut videos = sdk.videos(&token.open_id).page_size(10);
+//!     while let Some(video) = videos.try_next().await? {
+//!         let stats = sdk.video_stats(&token.open_id, &video.item_id, 7).await?;
+//!     }
+//!     // 上传并发布视频，大文件自动分片上传，失败后用同一个 session 重新调用会跳过已上传的分片
+//!     let mut session = None;
+//!     let progress = |p: UploadProgress| println!("{}/{}", p.uploaded, p.total);
+//!     let video = sdk.upload_video_file(&token.open_id, "clip.mp4", DEFAULT_VIDEO_PART_SIZE, &mut session, Some(&progress)).await?;
+//!     let item_id = sdk.create_video(&token.open_id, &CreateVideo::new(&video.video_id)).await?;
+//! ```
 pub mod sign;
+pub mod crypto;
+pub mod pay;
+pub mod trade;
+mod api;
+mod builder;
+mod error;
+mod limiter;
+mod login;
+mod notify;
+mod oauth;
+mod paginator;
+mod qrcode;
+mod refresher;
+mod registry;
+mod secret;
+mod security;
+mod signed;
+mod store;
+mod telemetry;
+mod upload;
+mod user;
+mod video;
+
+pub use builder::{RetryPolicy, SdkBuilder};
+pub use error::DouyinError;
+pub use limiter::{LimitMode, RateLimit, RateLimiter};
+pub use login::{Code2SessionData, Code2SessionRes};
+pub use notify::{SubscribeMessage, SubscribeMessageBuilder};
+pub use oauth::{MemoryUserTokenStore, UserToken, UserTokenStore, USER_TOKEN_INVALID_CODES};
+pub use paginator::{Page, Paginator, DEFAULT_PAGE_SIZE};
+pub use qrcode::{LinkExpire, QrCode, QrCodeColor, QrCodeOptions};
+pub use refresher::{RefresherConfig, RefresherHealth, TokenRefresher};
+pub use registry::{AppCredentials, SdkRegistry};
+pub use secret::Secret;
+pub use security::{ImageCheckResult, ImageSource, ModerationHit, TextCheckResult, TEXT_BATCH_SIZE};
+pub use signed::SignedRequest;
+pub use store::{CachedToken, FileTokenStore, MemoryTokenStore, TokenStore, TOKEN_CACHE_VERSION};
+pub use upload::{CreateVideo, UploadProgress, UploadProgressFn, UploadSession, UploadedVideo, DEFAULT_VIDEO_PART_SIZE, VIDEO_PART_MIN_SIZE, VIDEO_UPLOAD_MAX_SIZE};
+pub use user::{Fan, FansPage, FansStat, OpenUserInfo};
+pub use video::{Video, VideoDailyStat, VideoPage, VideoStatistics, VIDEO_DATA_BATCH_SIZE};
 
 use base64::Engine;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::{json, Value};
-use tokio::{
-    fs::{read_to_string, File},
-    io::{
-        AsyncWriteExt , // for write_all()
-        BufWriter
-    }
-};
+use api::{ApiResult, Endpoint};
 use rand::Rng;
 
 use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/**
+ * 单调时钟和系统时间的偏差超过多少秒视为系统时间发生了跳变
+ */
+const CLOCK_JUMP_THRESHOLD: i64 = 30;
+
+/**
+ * 接口返回这些错误码时认为 access_token 已失效，会清除缓存、重新获取 token 后重试一次
+ * 40002 access_token 无效，40004 access_token 过期，40022 access_token 错误
+ * 可以通过 SdkBuilder::token_invalid_codes 修改
+ */
+pub const TOKEN_INVALID_CODES: &[i64] = &[40002, 40004, 40022];
 
 /*
 * SDK 的结构
 */
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SDK {
     pub appid: String, // appid
-    pub secret: String, // secret
-    pub app_private_key: String,
+    pub secret: Secret, // secret
+    pub app_private_key: Secret,
     pub pkcs_type: sign::PkcsType, // app的私钥
     pub base_url: String, // 请求数据的url
     pub access_base_url: String, // 获取access_token的url
+    pub open_base_url: String, // 交易系统等 open.douyin.com 下接口的url
     pub access_token_cache_file_path: String, // access_token缓存文件路径，当进程重启后优先读取文件缓存
 
-    pub access_token: String, // access_token
-    pub expires_in: u64, // access_token的过期时间
+    pub access_token: Secret, // access_token
+    pub expires_in: u64, // access_token的过期时间戳，仅供参考，是否过期以单调时钟为准
+
+    private_key: Option<secret::SecretKey>, // 通过 builder 构造时提前解析好的私钥
+    client: reqwest::Client,
+    token_store: Arc<dyn TokenStore>,
+    user_token_store: Arc<dyn UserTokenStore>, // 用户授权的token
+    retry_policy: RetryPolicy,
+    rate_limiter: Option<Arc<RateLimiter>>, // clone 后共享
+    token_rx: Option<tokio::sync::watch::Receiver<Option<CachedToken>>>, // 后台刷新发布的token
+    token: Option<CachedToken>, // 当前使用的token
+    expires_at: Option<Instant>, // 内存中token的过期时间（单调时钟）
+    clock_anchor: Option<(Instant, u64)>, // 设置token时的单调时钟和系统时间，用来检测系统时间跳变
+    token_safety_margin: u64, // 提前多少秒刷新token
+    token_invalid_codes: Vec<i64>, // 表示token无效的错误码
+    revoked_token: Option<Secret>, // 最后一次作废的token
+    pay_salt: Secret, // 担保支付的签名salt
+    pay_token: Secret, // 担保支付回调验签的token
+    pay_callback_max_age: Option<Duration>, // 担保支付回调 timestamp 允许的最大偏差
 }
 
 /*
@@ -101,6 +278,20 @@ pub struct DouyinConfig<'a> {
     pub access_token_cache_file_path: Option<&'a str>,
 }
 
+impl std::fmt::Debug for DouyinConfig<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DouyinConfig")
+            .field("appid", &self.appid)
+            .field("secret", &"***")
+            .field("app_private_key", &"***")
+            .field("pkcs_type", &self.pkcs_type)
+            .field("base_url", &self.base_url)
+            .field("access_base_url", &self.access_base_url)
+            .field("access_token_cache_file_path", &self.access_token_cache_file_path)
+            .finish()
+    }
+}
+
 /*
 * get_access_token 的返回结构体
 */
@@ -112,7 +303,7 @@ pub struct AccessTokenRes {
 }
 #[derive(Deserialize, Serialize,Debug,Clone)]
 pub struct AccessTokenResData {
-    pub access_token: String,
+    pub access_token: Secret,
     pub expires_in: u64
 }
 
@@ -123,6 +314,18 @@ pub struct LiveOpenRes<T> {
     pub logid:String,
     pub data:T
 }
+
+impl<T> ApiResult for LiveOpenRes<T> {
+    fn code(&self) -> i64 {
+        self.err_no.into()
+    }
+    fn message(&self) -> String {
+        self.err_msg.clone()
+    }
+    fn logid(&self) -> &str {
+        &self.logid
+    }
+}
 #[derive(Deserialize, Serialize,Debug,Clone)]
 pub enum LiveOpenReqDataEnum {
     Start(LiveOpenReqDataStart),
@@ -145,6 +348,15 @@ pub struct RoomInfo {
     pub data: Option<RoomInfoData>,
 }
 
+impl ApiResult for RoomInfo {
+    fn code(&self) -> i64 {
+        self.errcode.unwrap_or_default().into()
+    }
+    fn message(&self) -> String {
+        self.errmsg.clone().unwrap_or_default()
+    }
+}
+
 #[derive(Debug,Serialize, Deserialize)]
 pub struct RoomInfoData {
     pub room_id: u64,
@@ -162,252 +374,381 @@ impl SDK  {
      * 构造函数初始化实例
      */
     pub fn new (config: DouyinConfig) -> Self {
+        let access_token_cache_file_path = match config.access_token_cache_file_path {
+            Some(path) => path.to_string(),
+            None => Self::get_exe_path(builder::DEFAULT_CACHE_FILE_NAME)
+                .map(|path| path.to_string_lossy().into_owned())
+                .unwrap_or_else(|_| builder::DEFAULT_CACHE_FILE_NAME.to_string()),
+        };
         // 将传入的参数和对象的默认值合并
         SDK {
             appid: String::from(config.appid),
-            secret: String::from(config.secret),
-            app_private_key: String::from(config.app_private_key),
-            access_base_url: config.access_base_url.unwrap_or("https://developer.toutiao.com").to_owned(),
-            base_url:  config.base_url.unwrap_or("https://webcast.bytedance.com").to_owned(),
-            pkcs_type: config.pkcs_type.unwrap_or(sign::PkcsType::Pkcs1),
-
-            access_token: "".to_string(),
-            access_token_cache_file_path: config.access_token_cache_file_path.unwrap_or(Self::get_exe_path("douyin_access_token.json").to_str().unwrap()).to_string(),
+            secret: Secret::from(config.secret),
+            app_private_key: Secret::from(config.app_private_key),
+            access_base_url: config.access_base_url.unwrap_or(builder::DEFAULT_ACCESS_BASE_URL).to_owned(),
+            open_base_url: builder::DEFAULT_OPEN_BASE_URL.to_owned(),
+            base_url:  config.base_url.unwrap_or(builder::DEFAULT_BASE_URL).to_owned(),
+            // 没有指定时根据私钥的PEM头判断，没有PEM头时按Pkcs8处理
+            pkcs_type: config.pkcs_type.unwrap_or_else(|| sign::detect_pkcs_type(config.app_private_key)),
+
+            access_token: Secret::default(),
+            token_store: Arc::new(FileTokenStore::new(&access_token_cache_file_path)),
+            access_token_cache_file_path,
             expires_in: 0,
+            private_key: None,
+            client: reqwest::Client::new(),
+            retry_policy: RetryPolicy::default(),
+            rate_limiter: None,
+            token_rx: None,
+            token: None,
+            expires_at: None,
+            clock_anchor: None,
+            token_safety_margin: builder::DEFAULT_TOKEN_SAFETY_MARGIN,
+            token_invalid_codes: TOKEN_INVALID_CODES.to_vec(),
+            revoked_token: None,
+            pay_salt: Secret::default(),
+            pay_token: Secret::default(),
+            pay_callback_max_age: None,
+            user_token_store: Arc::new(MemoryUserTokenStore::new()),
         }
     }
 
+    /**
+     * 获取构造器，构造时会校验参数
+     */
+    pub fn builder() -> SdkBuilder {
+        SdkBuilder::new()
+    }
+
     /**
      * 获取exe的路径，用来保存access_token缓存文件。
      */
-    fn get_exe_path(name: &str) -> std::path::PathBuf {
-        let current_exe_path = std::env::current_exe().unwrap();
-        let mut path = std::path::PathBuf::from(&current_exe_path);
+    pub(crate) fn get_exe_path(name: &str) -> std::io::Result<std::path::PathBuf> {
+        let current_exe_path = std::env::current_exe()?;
+        let mut path = current_exe_path;
         path.pop();
         path.push(name);
-        path
+        Ok(path)
     }
 
     /**
      * 请求失败后更新下次请求时间戳，避免频繁请求。
-     * 因为正常情况下token有7200秒的过期时间，此处为3600秒后开始更新token，正常情况下在剩余的一个小时内失败几次无所谓
-     * 只要成功一次即可，这里设计为超过3600秒后，如果请求失败，每1分钟请求一次。
+     * token会提前安全余量（默认600秒）开始更新，正常情况下在剩余的时间内失败几次无所谓
+     * 只要成功一次即可，这里设计为如果请求失败，每1分钟请求一次（间隔可通过 RetryPolicy 配置）。
      */
     fn update_expires_fallback_time(&mut self) {
-        self.expires_in = get_now_timestamp(false) + 60;
+        let fallback = self.retry_policy.token_fallback_secs;
+        self.expires_at = Some(Instant::now() + Duration::from_secs(fallback));
+        self.expires_in = get_now_timestamp(false) + fallback;
+    }
+
+    /**
+     * 使用缓存的token，扣除安全余量后已过期返回 false
+     */
+    fn use_cached_token(&mut self, data: CachedToken) -> bool {
+        // 已作废的token不再使用（后台刷新或其他进程可能还没有更新）
+        if self.revoked_token.as_ref() == Some(&data.access_token) {
+            return false;
+        }
+        let now = get_now_timestamp(false);
+        match data.remaining(&self.appid, now, self.token_safety_margin) {
+            Some(remaining) => {
+                self.access_token = data.access_token.clone();
+                self.expires_at = Some(Instant::now() + remaining);
+                self.expires_in = now + remaining.as_secs();
+                self.clock_anchor = Some((Instant::now(), now));
+                self.token = Some(data);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /**
+     * 检测系统时间是否发生跳变，跳变后修正 expires_in
+     * 内存中的过期判断使用单调时钟，不受影响
+     */
+    fn detect_clock_jump(&mut self) {
+        if let Some((instant, wall)) = self.clock_anchor {
+            let now = get_now_timestamp(false);
+            let drift = (now as i64 - wall as i64) - instant.elapsed().as_secs() as i64;
+            if drift.abs() > CLOCK_JUMP_THRESHOLD {
+                telemetry::clock_jump(&self.appid, drift);
+                if let Some(expires_at) = self.expires_at {
+                    self.expires_in = now + expires_at.saturating_duration_since(Instant::now()).as_secs();
+                }
+                self.clock_anchor = Some((Instant::now(), now));
+            }
+        }
     }
 
     /**
      * 获取access_token
      */ 
-    pub async fn get_access_token (&mut self) -> Result<String,Box<dyn std::error::Error>> {
-        let ts: u64 = get_now_timestamp(false);
-        // 如果已过期
-        if self.expires_in < ts {
-            // 读取文件缓存，判断是否过期
-            let read_data = Self::read_file(&self.access_token_cache_file_path).await;
-            // 如果文件缓存过期
-            if read_data.expires_in < ts {
-                let res: Result<reqwest::Response, reqwest::Error>= self.access_token_request("/api/apps/v2/token",
-                    json!({
-                        "appid":self.appid,
-                        "secret":self.secret,
-                        "grant_type":"client_credential" // 获取 access_token 时值为 client_credential
-                    })
-                ).await;
-                if let Ok(resopnse) = res {
-                    let access_token_res = resopnse.json::<AccessTokenRes>().await;
-                    if let Ok(data) = access_token_res {
-                        if data.err_no == 0 {
-                            // 如果过期时间大于1小时，则缩短到一小时，否则直接使用过期时间
-                            let expires;
-                            if data.data.expires_in > 3600 {
-                                expires = 3600 + ts;
-                            } else {
-                                expires = data.data.expires_in + ts;
-                            }
-                            let token = data.data.access_token;
-                            // 写入文件
-                            Self::write_file(AccessTokenResData {
-                                expires_in: expires,
-                                access_token: token.clone()
-                            }, &self.access_token_cache_file_path).await?;
-                            self.access_token = token;
-                            self.expires_in = expires;
-                        } else {
-                            // -1 系统错误
-                            // 40015 appid 错误
-                            // 40017 secret 错误
-                            // 40020 grant_type 不是 client_credential
-                            // 其它 参数为空
-                            self.update_expires_fallback_time();
-                            return Err(format!("获取access_token失败,err_no:{}, err_msg: {}",data.err_no, data.err_tips).into());
-                        }
-                    } else {
-                        self.update_expires_fallback_time();
-                        return Err("json解析失败".into());
-                    }
-                } else {
+    pub async fn get_access_token (&mut self) -> Result<String, DouyinError> {
+        self.detect_clock_jump();
+        let expired = |sdk: &Self| sdk.expires_at.is_none_or(|at| at <= Instant::now());
+        // 如果已过期，优先使用后台刷新的token
+        if expired(self) {
+            let refreshed = self.token_rx.as_ref().and_then(|rx| rx.borrow().clone());
+            if let Some(data) = refreshed {
+                self.use_cached_token(data);
+            }
+        }
+        if expired(self) {
+            // 读取缓存，判断是否过期
+            let cached = self.token_store.load(&self.appid).await;
+            if cached.is_some_and(|data| self.use_cached_token(data)) {
+                telemetry::token_cache_hit(&self.appid);
+            } else {
+                // 如果缓存过期
+                telemetry::token_cache_miss(&self.appid);
+                if let Err(err) = self.refresh_access_token().await {
+                    telemetry::token_refresh_failed(&self.appid, &err);
                     self.update_expires_fallback_time();
-                    return Err("请求失败".into());
+                    return Err(err);
                 }
-            } else {
-                // 把文件的过期时间写入内存
-                self.access_token = read_data.access_token;
-                self.expires_in = read_data.expires_in;
             }
         }
-        return Ok(self.access_token.clone());
+        // 获取失败后的等待时间内不会重新请求，此时没有token或token已经过期的话返回错误，而不是用空的token发请求
+        let now = get_now_timestamp(false);
+        if self.access_token.is_empty() || self.token.as_ref().is_some_and(|data| data.expires_at() <= now) {
+            return Err(DouyinError::TokenUnavailable);
+        }
+        Ok(self.access_token.expose().to_string())
     }
 
     /**
-     * 写入access_token到缓存文件
+     * 强制作废当前的access_token，同时清除缓存，下次请求时会重新获取
+     * 用于 secret 重置等 token 被提前吊销的情况
+     * 缓存中的token不是当前的token时（其他实例已经刷新过）保留缓存，下次请求直接使用
      */
-    async fn write_file(data:AccessTokenResData, file_name: &str) -> Result<(), std::io::Error> {
-        let path: std::path::PathBuf = Self::get_exe_path(file_name);
-        let file = File::create(&path).await;
-        match file {
-            Ok(f) => {
-                let mut writer = BufWriter::new(f);
-                let json_str = serde_json::to_string(&data);
-                match json_str {
-                    Ok(str) => {
-                        let _ = writer.write_all(str.as_bytes()).await;
-                        let _ = writer.flush().await;
-                        println!("写入文件成功");
-                        return Ok(());
-                    },
-                    Err(err) => {
-                        println!("toml解析失败:{:?}",err);
-                        return Ok(());
-                    }
-                }
-            },
-            Err(err) => {
-                println!("写入文件失败:{:?}",err);
-                return Ok(());
-            }
+    pub async fn invalidate_access_token(&mut self) {
+        telemetry::token_invalidated(&self.appid);
+        let revoked = std::mem::take(&mut self.access_token);
+        self.expires_in = 0;
+        self.expires_at = None;
+        self.token = None;
+        if revoked.is_empty() {
+            return;
+        }
+        if let Err(err) = self.token_store.remove_if_matches(&self.appid, &revoked).await {
+            telemetry::token_save_failed(&self.appid, &err);
         }
+        self.revoked_token = Some(revoked);
     }
 
     /**
-     * 读取access_token文件
+     * 同步另一个实例（例如 clone 出去发请求的副本）中的 token
+     * 对方作废过的 token 在这里也作废，对方的 token 过期时间更晚时使用对方的 token
      */
-    async fn read_file(file_name: &str) -> AccessTokenResData {
-        let path = Self::get_exe_path(file_name);
-        let contents= read_to_string(&path).await;
-        if let Ok(json_text) = contents{
-            let result: Result<AccessTokenResData, serde_json::Error> = serde_json::from_str(&json_text);
-            if let Ok(data) = result {
-                return data;
-            } else if let Err(err) = result {
-                println!("Info: json解析错误10分钟后重试: {:#?}", err);
+    pub(crate) fn sync_token_from(&mut self, other: &SDK) {
+        if let Some(revoked) = &other.revoked_token {
+            if self.access_token == *revoked {
+                self.access_token = Secret::default();
+                self.expires_in = 0;
+                self.expires_at = None;
+                self.token = None;
             }
-        } else if let Err(err) = contents {
-            println!("Info: token缓存文件不存在-程序继续执行, : {:?}", err);
+            self.revoked_token = Some(revoked.clone());
         }
-        // 读取失败，返回默认值，稍后转到请求数据
-        return AccessTokenResData{
-            access_token: "".to_string(),
-            expires_in: 0,
+        if !other.access_token.is_empty() && other.expires_at > self.expires_at {
+            self.access_token = other.access_token.clone();
+            self.expires_in = other.expires_in;
+            self.expires_at = other.expires_at;
+            self.clock_anchor = other.clock_anchor;
+            self.token = other.token.clone();
         }
     }
-    async fn access_token_request(&self,path:&str,map:Value) -> Result<reqwest::Response, reqwest::Error> {
-        let client = reqwest::Client::new();
-        let res = client.post(format!("{}{}", self.access_base_url , path))
-            .header("Accept", "application/json")
-            .json(&map) 
-            .send()
-            .await;
-        return res;
-    }
-    pub async fn sign_request(&mut self,path:&str,body:Value) -> Result<reqwest::Response, reqwest::Error> {
-        let http_method = "POST";
-        let timestamp = get_now_timestamp(false).to_string();
-        let random_string = make_random_string();
-        let json_str = serde_json::to_string(&body).unwrap();
-        let sign_str = format!(
-            "{}\n{}\n{}\n{}\n{}\n",
-            http_method, path, timestamp, random_string, json_str
-        );
-        
-        let base64_str  = sign::sign_base64(sign_str.as_bytes(),&self.app_private_key,&sign::PkcsType::Pkcs8);
-
-        let byte_authorization = format!(
-            "SHA256-RSA2048 appid=\"{}\",nonce_str=\"{}\",timestamp=\"{}\",key_version=\"1\",signature=\"{}\"",
-            self.appid, random_string, timestamp,base64_str
-        );
-        let client = reqwest::Client::new();
-        let access_token = self.get_access_token().await.expect("获取access_token失败");
-        let res = client.post(format!("{}{}", self.base_url, path))
-            .header("Byte-Authorization", byte_authorization)
+
+    /**
+     * 接口返回token无效的错误码时作废token，返回是否需要重试
+     */
+    pub(crate) async fn invalidate_if_token_error(&mut self, code: i64) -> bool {
+        if code == 0 || !self.token_invalid_codes.contains(&code) {
+            return false;
+        }
+        self.invalidate_access_token().await;
+        true
+    }
+
+    /**
+     * 请求新的access_token并写入缓存
+     */
+    async fn refresh_access_token(&mut self) -> Result<(), DouyinError> {
+        let resopnse = self.access_token_request("/api/apps/v2/token",
+            json!({
+                "appid":self.appid,
+                "secret":self.secret.expose(),
+                "grant_type":"client_credential" // 获取 access_token 时值为 client_credential
+            })
+        ).await?;
+        let data = resopnse.json::<AccessTokenRes>().await?;
+        telemetry::api_error("/api/apps/v2/token", data.err_no.into());
+        if data.err_no != 0 {
+            // -1 系统错误
+            // 40015 appid 错误
+            // 40017 secret 错误
+            // 40020 grant_type 不是 client_credential
+            // 其它 参数为空
+            return Err(DouyinError::Api { code: data.err_no.into(), msg: data.err_tips });
+        }
+        if self.revoked_token.as_ref() == Some(&data.data.access_token) {
+            // 不写入缓存；清除作废记录，等待 fallback 时间后再次获取时接受平台返回的token
+            self.revoked_token = None;
+            return Err(DouyinError::RevokedTokenReissued);
+        }
+        let cached = CachedToken::new(&self.appid, data.data.access_token, data.data.expires_in);
+        // 写入缓存，失败时只记录，token本身仍然可以使用
+        if let Err(err) = self.token_store.save(&self.appid, &cached).await {
+            telemetry::token_save_failed(&self.appid, &err);
+        }
+        telemetry::token_refreshed(&self.appid, cached.expires_in);
+        if !self.use_cached_token(cached) {
+            return Err(DouyinError::Api { code: -1, msg: format!("access_token有效期过短: {}秒", data.data.expires_in) });
+        }
+        Ok(())
+    }
+
+    /**
+     * 发送请求，先经过限流，网络错误时按 RetryPolicy 重试
+     */
+    async fn send(&self, path: &str, request: reqwest::RequestBuilder) -> Result<reqwest::Response, DouyinError> {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire(path).await?;
+        }
+        let span = telemetry::CallSpan::request(path);
+        let res = span.instrument(async {
+            let mut attempt: u32 = 0;
+            loop {
+                span.attempt(attempt + 1);
+                let current = match request.try_clone() {
+                    Some(current) => current,
+                    // 无法复制的请求（例如流式body）不重试
+                    None => return request.send().await,
+                };
+                match current.send().await {
+                    Err(err) if attempt < self.retry_policy.max_retries && (err.is_connect() || err.is_timeout()) => {
+                        attempt += 1;
+                        tokio::time::sleep(self.retry_policy.backoff * attempt).await;
+                    },
+                    res => return res,
+                }
+            }
+        }).await;
+        if let Ok(response) = &res {
+            span.status(response.status().as_u16());
+        }
+        span.finish(&res);
+        Ok(res?)
+    }
+
+    async fn access_token_request(&self,path:&str,map:Value) -> Result<reqwest::Response, DouyinError> {
+        let request = self.client.post(format!("{}{}", self.access_base_url , path))
             .header("Accept", "application/json")
-            .header(reqwest::header::CONTENT_TYPE, "application/json")
-            .header("access-token", access_token)
-            .json(&body) 
-            .send()
-            .await;
-        return res;
+            .json(&map);
+        self.send(path, request).await
+    }
+
+    pub async fn sign_request(&mut self,path:&str,body:Value) -> Result<reqwest::Response, DouyinError> {
+        let base_url = self.base_url.clone();
+        self.sign_request_with(reqwest::Method::POST, &base_url, path, &[], Some(body)).await
+    }
+
+    /**
+     * 带 Byte-Authorization 签名的请求，支持任意请求方法和 query 参数
+     * 签名的路径包含 query，没有 body 时（例如 GET）签名中的 body 为空字符串
+     */
+    pub async fn sign_request_with(&mut self,method:reqwest::Method,base_url:&str,path:&str,query:&[(&str,&str)],body:Option<Value>) -> Result<reqwest::Response, DouyinError> {
+        let mut sign_path = path.to_string();
+        if !query.is_empty() {
+            // 只借用 Url 做 query 编码
+            let mut url = reqwest::Url::parse("http://localhost/").expect("常量url");
+            url.query_pairs_mut().extend_pairs(query);
+            sign_path.push(if path.contains('?') { '&' } else { '?' });
+            sign_path.push_str(url.query().unwrap_or_default());
+        }
+        let mut request = SignedRequest::new(method, &sign_path).base_url(base_url);
+        if let Some(body) = &body {
+            request = request.json(body)?;
+        }
+        self.send_signed(request).await
     }
 
     
-    pub async fn access_request(&mut self,path:&str,body:Value) -> Result<reqwest::Response, reqwest::Error> {
-        let client = reqwest::Client::new();
-        let access_token = self.get_access_token().await.expect("获取access_token失败");
-        let res = client.post(format!("{}{}", self.base_url, path))
+    pub async fn access_request(&mut self,path:&str,body:Value) -> Result<reqwest::Response, DouyinError> {
+        let base_url = self.base_url.clone();
+        self.x_token_request(&base_url, path, body).await
+    }
+
+    /**
+     * 与 access_request 相同，但请求 access_base_url（developer.toutiao.com）下的接口
+     */
+    pub async fn developer_request(&mut self,path:&str,body:Value) -> Result<reqwest::Response, DouyinError> {
+        let access_base_url = self.access_base_url.clone();
+        self.x_token_request(&access_base_url, path, body).await
+    }
+
+    async fn x_token_request(&mut self,base_url:&str,path:&str,body:Value) -> Result<reqwest::Response, DouyinError> {
+        let access_token = self.get_access_token().await?;
+        let request = self.client.post(format!("{}{}", base_url, path))
             .header("Accept", "application/json")
             .header(reqwest::header::CONTENT_TYPE, "application/json")
             .header("X-Token", access_token)
-            .json(&body) 
-            .send()
-            .await;
-        return res;
+            .json(&body);
+        self.send(path, request).await
     }
     /**
      * post请求task 
      */
-    pub async fn task<T>(&mut self,task:&str,roomid:&str,appid:&str,msg_type:&str) -> Result<LiveOpenRes<T>, reqwest::Error>
+    pub async fn task<T>(&mut self,task:&str,roomid:&str,appid:&str,msg_type:&str) -> Result<LiveOpenRes<T>, DouyinError>
         where T: DeserializeOwned
     {
         let path = format!("/api/live_data/task/{}",task);
-        let result: Result<reqwest::Response, reqwest::Error> = self.sign_request(&path[..],json!({"roomid":roomid,"appid":appid,"msg_type":msg_type})).await;
-        let res = result?.json::<LiveOpenRes<T>>().await;
-        return res;
+        let span = telemetry::CallSpan::task(&path, roomid, msg_type);
+        let body = json!({"roomid":roomid,"appid":appid,"msg_type":msg_type});
+        let base_url = self.base_url.clone();
+        self.retry_on_token_error(span, Endpoint::Signed(&base_url), &path, &body, api::json::<LiveOpenRes<T>>).await
     }
 
     /**
      * 使用 access_token 获取 直播间信息
      */
-    pub async fn info(&mut self,token:&str) -> Result<RoomInfo, reqwest::Error> {
-        let result: Result<reqwest::Response, reqwest::Error> = self.access_request("/api/webcastmate/info",json!({"token":token})).await;
-        let res = result?.json::<RoomInfo>().await;
-        return res;
+    pub async fn info(&mut self,token:&str) -> Result<RoomInfo, DouyinError> {
+        let span = telemetry::CallSpan::call("/api/webcastmate/info");
+        let body = json!({"token":token});
+        self.retry_on_token_error(span, Endpoint::Access, "/api/webcastmate/info", &body, api::json::<RoomInfo>).await
     }
 
     /**
      * verify sign 回调验证签名
      */
     pub fn verify_sign(&mut self,map:BTreeMap<&str,&str>,body:&str,app_secret:&str) -> String {
-        let sign_str = map_2_str(map);
-        let raw_data = format!("{}{}{}",sign_str,body,app_secret);
-        let md5_val = md5::compute(&raw_data[..]);
-        let bytes =  md5_val.0;
-        base64::engine::general_purpose::STANDARD.encode(bytes)
+        push_sign(map, body, app_secret)
     }
 }
 
+/**
+ * 计算推送回调的签名，与 SDK::verify_sign 一致，不需要 SDK 实例
+ */
+pub fn push_sign(map:BTreeMap<&str,&str>,body:&str,app_secret:&str) -> String {
+    let sign_str = map_2_str(map);
+    let raw_data = format!("{}{}{}",sign_str,body,app_secret);
+    let md5_val = md5::compute(&raw_data[..]);
+    let bytes =  md5_val.0;
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
 /**
  * 按字典序拼接 header 字符串
  */
 pub fn map_2_str(header:BTreeMap<&str,&str>) -> String {
     let mut header_str = String::new();
-    let mut i: usize = 1;
-    for (key, value) in &header {
+    for (i, (key, value)) in header.iter().enumerate() {
         header_str.push_str(&format!("{}={}", key, value));
-        if i !=0 && i < header.len() {
-            header_str.push_str("&");
+        if i + 1 < header.len() {
+            header_str.push('&');
         }
-        i+= 1;
     }
     header_str
 }
@@ -435,8 +776,122 @@ pub fn get_now_timestamp(ms: bool) -> u64 {
     let since_epoch = now.duration_since(std::time::UNIX_EPOCH).expect("时光倒流");
     // 获取毫秒级时间戳
     if ms {
-        return since_epoch.as_secs() * 1000 + since_epoch.subsec_millis() as u64;
+        since_epoch.as_secs() * 1000 + since_epoch.subsec_millis() as u64
     } else {
-        return since_epoch.as_secs()
+        since_epoch.as_secs()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /**
+     * 按顺序返回 responses 中 json 的本地 http 服务，返回 base_url 和收到的请求（请求行和 body）
+     */
+    pub(crate) async fn stub_server(responses: Vec<&'static str>) -> (String, Arc<std::sync::Mutex<Vec<String>>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            for body in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 4096];
+                // 读完请求头和 content-length 指定的 body
+                loop {
+                    let n = stream.read(&mut chunk).await.unwrap();
+                    buf.extend_from_slice(&chunk[..n]);
+                    let text = String::from_utf8_lossy(&buf).to_string();
+                    if let Some(end) = text.find("\r\n\r\n") {
+                        let length = text.lines()
+                            .find_map(|line| line.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                            .unwrap_or(0);
+                        if n == 0 || buf.len() >= end + 4 + length {
+                            let request_line = text.lines().next().unwrap_or_default().to_string();
+                            recorded.lock().unwrap().push(format!("{}\n{}", request_line, &text[end + 4..]));
+                            break;
+                        }
+                    }
+                    if n == 0 {
+                        break;
+                    }
+                }
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(), body
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+                stream.shutdown().await.ok();
+            }
+        });
+        (base_url, requests)
+    }
+
+    /**
+     * 使用内存缓存，获取 token 的请求会立即失败（连接被拒绝）
+     */
+    pub(crate) fn offline_sdk(store: Arc<MemoryTokenStore>) -> SDK {
+        SDK::builder()
+            .appid("appid")
+            .secret("secret")
+            .access_base_url("http://127.0.0.1:1")
+            .token_store(store)
+            .retry_policy(RetryPolicy { max_retries: 0, ..Default::default() })
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn sign_request_with_encodes_query() {
+        let (base_url, requests) = stub_server(vec!["{}", "{}", "{}"]).await;
+        let store = Arc::new(MemoryTokenStore::new());
+        store.save("appid", &CachedToken::new("appid", "token".into(), 7200)).await.unwrap();
+        let mut sdk = SDK::builder()
+            .appid("appid")
+            .secret("secret")
+            .app_private_key(include_str!("../examples/private_key.pem"))
+            .token_store(store)
+            .build()
+            .unwrap();
+
+        let query = [("a", "1 2"), ("b", "x&y=z"), ("c", "中")];
+        sdk.sign_request_with(reqwest::Method::GET, &base_url, "/path", &query, None).await.unwrap();
+        sdk.sign_request_with(reqwest::Method::GET, &base_url, "/path?d=4", &query, None).await.unwrap();
+        sdk.sign_request_with(reqwest::Method::GET, &base_url, "/path?d=4", &[], None).await.unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert!(requests[0].starts_with("GET /path?a=1+2&b=x%26y%3Dz&c=%E4%B8%AD HTTP/1.1"));
+        assert!(requests[1].starts_with("GET /path?d=4&a=1+2&b=x%26y%3Dz&c=%E4%B8%AD HTTP/1.1"));
+        assert!(requests[2].starts_with("GET /path?d=4 HTTP/1.1"));
+    }
+
+    #[tokio::test]
+    async fn no_empty_token_after_failed_refresh() {
+        let store = Arc::new(MemoryTokenStore::new());
+        store.save("appid", &CachedToken::new("appid", "token1".into(), 7200)).await.unwrap();
+        let mut sdk = offline_sdk(store.clone());
+        assert_eq!(sdk.get_access_token().await.unwrap(), "token1");
+
+        sdk.invalidate_access_token().await;
+        assert!(store.load("appid").await.is_none());
+        assert!(matches!(sdk.get_access_token().await, Err(DouyinError::Http(_))));
+        // 等待重新获取期间不会返回空的token
+        assert!(matches!(sdk.get_access_token().await, Err(DouyinError::TokenUnavailable)));
+        assert!(matches!(sdk.info("token").await, Err(DouyinError::TokenUnavailable)));
+    }
+
+    #[tokio::test]
+    async fn expired_token_is_not_used_during_fallback() {
+        let store = Arc::new(MemoryTokenStore::new());
+        let mut sdk = offline_sdk(store.clone());
+        let mut expired = CachedToken::new("appid", "token1".into(), 7200);
+        expired.issued_at -= 7200;
+        sdk.access_token = expired.access_token.clone();
+        sdk.token = Some(expired);
+        assert!(sdk.get_access_token().await.is_err());
+        assert!(matches!(sdk.get_access_token().await, Err(DouyinError::TokenUnavailable)));
+    }
+}
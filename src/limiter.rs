@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::DouyinError;
+
+/**
+ * 超出限额时的处理方式
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitMode {
+    /// 排队等待，直到拿到令牌。max_wait 为最长等待时间，需要等待更久时直接返回错误
+    Queue { max_wait: Option<Duration> },
+    /// 没有令牌时直接返回 DouyinError::RateLimited
+    FailFast,
+}
+
+impl Default for LimitMode {
+    fn default() -> Self {
+        LimitMode::Queue { max_wait: None }
+    }
+}
+
+/**
+ * 单个接口的限额
+ * qps 不是正数（0、负数、NaN）时不补充令牌，用完后直接返回错误；burst 小于1时按1处理
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub qps: f64, // 每秒补充的令牌数
+    pub burst: u32, // 令牌桶容量，允许的突发请求数
+}
+
+impl RateLimit {
+    pub fn new(qps: f64, burst: u32) -> Self {
+        RateLimit { qps, burst }
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    limit: RateLimit,
+    tokens: f64,
+    last: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        if self.limit.qps > 0.0 {
+            self.tokens = (self.tokens + elapsed * self.limit.qps).min(self.limit.burst.max(1) as f64);
+        }
+        self.last = now;
+    }
+}
+
+/**
+ * 客户端令牌桶限流，按接口路径配置
+ * 路径按最长前缀匹配，例如配置 `/api/live_data/task` 会同时限制 start/stop/status，
+ * 没有配置的路径不限流。SDK clone 后共享同一个限流器。
+ */
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    mode: LimitMode,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(mode: LimitMode) -> Self {
+        RateLimiter {
+            mode,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /**
+     * 设置接口的限额，path 可以是完整路径或前缀
+     */
+    pub fn limit(self, path: &str, limit: RateLimit) -> Self {
+        self.buckets.lock().unwrap().insert(path.to_string(), Bucket {
+            limit,
+            tokens: limit.burst.max(1) as f64,
+            last: Instant::now(),
+        });
+        self
+    }
+
+    /**
+     * 获取一个令牌，排队模式下会等待
+     */
+    pub async fn acquire(&self, path: &str) -> Result<(), DouyinError> {
+        let wait = {
+            let mut buckets = self.buckets.lock().unwrap();
+            let key = buckets.keys()
+                .filter(|key| path.starts_with(key.as_str()))
+                .max_by_key(|key| key.len())
+                .cloned();
+            let bucket = match key.and_then(|key| buckets.get_mut(&key)) {
+                Some(bucket) => bucket,
+                None => return Ok(()),
+            };
+            bucket.refill(Instant::now());
+            if bucket.tokens >= 1.0 {
+                bucket.tokens -= 1.0;
+                return Ok(());
+            }
+            let max_wait = match self.mode {
+                LimitMode::FailFast => return Err(DouyinError::RateLimited(path.to_string())),
+                LimitMode::Queue { max_wait } => max_wait,
+            };
+            // qps 不是正数或太小时等待时间无法表示，直接返回错误
+            let wait = match Duration::try_from_secs_f64((1.0 - bucket.tokens) / bucket.limit.qps) {
+                Ok(wait) if max_wait.is_none_or(|max_wait| wait <= max_wait) => wait,
+                _ => return Err(DouyinError::RateLimited(path.to_string())),
+            };
+            // 先预占令牌再等待，后来的请求会排在后面
+            bucket.tokens -= 1.0;
+            wait
+        };
+        tokio::time::sleep(wait).await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(mode: LimitMode, qps: f64, burst: u32) -> RateLimiter {
+        RateLimiter::new(mode).limit("/api/live_data/task", RateLimit::new(qps, burst))
+    }
+
+    fn is_limited(res: Result<(), DouyinError>) -> bool {
+        matches!(res, Err(DouyinError::RateLimited(_)))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn fail_fast_refills_over_time() {
+        let limiter = limiter(LimitMode::FailFast, 1.0, 2)
+            .limit("/api/live_data/task/status", RateLimit::new(100.0, 100));
+        assert!(limiter.acquire("/api/live_data/task/start").await.is_ok());
+        assert!(limiter.acquire("/api/live_data/task/stop").await.is_ok());
+        assert!(is_limited(limiter.acquire("/api/live_data/task/start").await));
+        // 最长前缀匹配到单独配置的 status，没有配置的路径不限流
+        assert!(limiter.acquire("/api/live_data/task/status").await.is_ok());
+        assert!(limiter.acquire("/api/webcastmate/info").await.is_ok());
+
+        tokio::time::advance(Duration::from_millis(1500)).await;
+        assert!(limiter.acquire("/api/live_data/task/start").await.is_ok());
+        assert!(is_limited(limiter.acquire("/api/live_data/task/start").await));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn queue_waits_in_order() {
+        let limiter = limiter(LimitMode::Queue { max_wait: None }, 2.0, 1);
+        let start = Instant::now();
+        limiter.acquire("/api/live_data/task/start").await.unwrap();
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        limiter.acquire("/api/live_data/task/start").await.unwrap();
+        assert_eq!(start.elapsed(), Duration::from_millis(500));
+
+        let (a, b) = tokio::join!(limiter.acquire("/api/live_data/task/start"), limiter.acquire("/api/live_data/task/start"));
+        assert!(a.is_ok() && b.is_ok());
+        assert_eq!(start.elapsed(), Duration::from_millis(1500));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn queue_respects_max_wait() {
+        let limiter = limiter(LimitMode::Queue { max_wait: Some(Duration::from_millis(500)) }, 1.0, 1);
+        limiter.acquire("/api/live_data/task/start").await.unwrap();
+        assert!(is_limited(limiter.acquire("/api/live_data/task/start").await));
+        tokio::time::advance(Duration::from_millis(600)).await;
+        let start = Instant::now();
+        limiter.acquire("/api/live_data/task/start").await.unwrap();
+        assert_eq!(start.elapsed(), Duration::from_millis(400));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn invalid_qps_does_not_panic() {
+        for qps in [1e-20, 0.0, -1.0, f64::NAN] {
+            for mode in [LimitMode::Queue { max_wait: None }, LimitMode::FailFast] {
+                let limiter = limiter(mode, qps, 0);
+                // burst 为0时按1处理
+                assert!(limiter.acquire("/api/live_data/task/start").await.is_ok());
+                tokio::time::advance(Duration::from_secs(3600)).await;
+                assert!(is_limited(limiter.acquire("/api/live_data/task/start").await), "qps: {}", qps);
+            }
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{api::ApiResult, telemetry, DouyinError, Secret, SDK};
+
+/*
+* code2session 的返回结构体
+*/
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Code2SessionRes {
+    pub err_no: i32,
+    pub err_tips: String,
+    #[serde(default)]
+    pub data: Code2SessionData,
+}
+
+impl ApiResult for Code2SessionRes {
+    fn code(&self) -> i64 {
+        self.err_no.into()
+    }
+    fn message(&self) -> String {
+        self.err_tips.clone()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Code2SessionData {
+    pub session_key: Secret, // 会话密钥，用于解密用户敏感数据，不要下发到客户端
+    pub openid: String, // 用户在当前小程序的 ID，使用 code 登录时返回
+    pub anonymous_openid: String, // 匿名用户在当前小程序的 ID，使用 anonymous_code 登录时返回
+    pub unionid: String, // 用户在小程序平台的唯一标识符
+}
+
+impl SDK {
+    /**
+     * 小程序登录，用 tt.login 获取的 code 或 anonymous_code 换取 openid 和 session_key
+     * code 和 anonymous_code 至少传一个
+     */
+    pub async fn code2session(&self, code: Option<&str>, anonymous_code: Option<&str>) -> Result<Code2SessionData, DouyinError> {
+        if code.is_none() && anonymous_code.is_none() {
+            return Err(DouyinError::InvalidParam("code和anonymous_code不能都为空".into()));
+        }
+        let path = "/api/apps/v2/jscode2session";
+        let span = telemetry::CallSpan::call(path);
+        let data = span.trace(async {
+            let response = self.access_token_request(path, json!({
+                "appid": self.appid,
+                "secret": self.secret.expose(),
+                "code": code.unwrap_or_default(),
+                "anonymous_code": anonymous_code.unwrap_or_default(),
+            })).await?;
+            Ok(response.json::<Code2SessionRes>().await?)
+        }).await?;
+        if data.err_no != 0 {
+            // 40014 参数错误
+            // 40015 appid 错误
+            // 40017 secret 错误
+            // 40018 code 错误
+            // 40019 anonymous_code 错误
+            return Err(DouyinError::Api { code: data.err_no.into(), msg: data.err_tips });
+        }
+        Ok(data.data)
+    }
+}
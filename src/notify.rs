@@ -0,0 +1,174 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use serde_json::json;
+
+use crate::{api::BaseRes, DouyinError, SDK};
+
+/**
+ * 订阅消息，通过 SubscribeMessage::builder 构造
+ */
+#[derive(Serialize, Debug, Clone)]
+pub struct SubscribeMessage {
+    pub tpl_id: String, // 模板id
+    pub open_id: String, // 接收消息的用户
+    pub data: BTreeMap<String, String>, // 模板内容，key 为模板中的字段名
+    pub page: Option<String>, // 点击消息跳转的小程序页面
+}
+
+impl SubscribeMessage {
+    pub fn builder(tpl_id: &str, open_id: &str) -> SubscribeMessageBuilder {
+        SubscribeMessageBuilder {
+            tpl_id: tpl_id.to_string(),
+            open_id: open_id.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+/**
+ * 订阅消息的构造器，build 时校验字段名
+ */
+#[derive(Debug, Clone, Default)]
+pub struct SubscribeMessageBuilder {
+    tpl_id: String,
+    open_id: String,
+    fields: Vec<(String, String)>,
+    page: Option<String>,
+    template_fields: Option<Vec<String>>,
+}
+
+impl SubscribeMessageBuilder {
+    /**
+     * 添加模板字段，例如 field("thing1", "对局结束")
+     */
+    pub fn field(mut self, name: &str, value: &str) -> Self {
+        self.fields.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn page(mut self, page: &str) -> Self {
+        self.page = Some(page.to_string());
+        self
+    }
+
+    /**
+     * 模板中定义的字段名（开发者后台的模板详情中可以看到），设置后 build 时校验字段是否完全一致
+     */
+    pub fn template_fields(mut self, names: &[&str]) -> Self {
+        self.template_fields = Some(names.iter().map(|name| name.to_string()).collect());
+        self
+    }
+
+    pub fn build(self) -> Result<SubscribeMessage, DouyinError> {
+        if self.tpl_id.is_empty() {
+            return Err(DouyinError::InvalidParam("tpl_id不能为空".into()));
+        }
+        if self.open_id.is_empty() {
+            return Err(DouyinError::InvalidParam("open_id不能为空".into()));
+        }
+        if self.fields.is_empty() {
+            return Err(DouyinError::InvalidParam("模板字段不能为空".into()));
+        }
+        let mut data = BTreeMap::new();
+        for (name, value) in self.fields {
+            // 字段名为字母开头的字母、数字、下划线，例如 thing1、time2
+            let valid = name.starts_with(|c: char| c.is_ascii_alphabetic())
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid {
+                return Err(DouyinError::InvalidParam(format!("模板字段名不合法: {}", name)));
+            }
+            if value.is_empty() {
+                return Err(DouyinError::InvalidParam(format!("模板字段的值不能为空: {}", name)));
+            }
+            if data.insert(name.clone(), value).is_some() {
+                return Err(DouyinError::InvalidParam(format!("模板字段重复: {}", name)));
+            }
+        }
+        if let Some(template_fields) = &self.template_fields {
+            if let Some(name) = data.keys().find(|name| !template_fields.contains(name)) {
+                return Err(DouyinError::InvalidParam(format!("模板中没有该字段: {}", name)));
+            }
+            if let Some(name) = template_fields.iter().find(|name| !data.contains_key(*name)) {
+                return Err(DouyinError::InvalidParam(format!("缺少模板字段: {}", name)));
+            }
+        }
+        Ok(SubscribeMessage {
+            tpl_id: self.tpl_id,
+            open_id: self.open_id,
+            data,
+            page: self.page,
+        })
+    }
+}
+
+impl SDK {
+    /**
+     * 发送订阅消息，用户需要先在小程序中订阅该模板
+     * 平台返回的错误码通过 DouyinError::Api 返回
+     */
+    pub async fn send_subscribe_message(&mut self, message: &SubscribeMessage) -> Result<(), DouyinError> {
+        let mut body = json!({
+            "app_id": self.appid,
+            "tpl_id": message.tpl_id,
+            "open_id": message.open_id,
+            "data": message.data,
+        });
+        if let Some(page) = &message.page {
+            body["page"] = json!(page);
+        }
+        self.call_api::<BaseRes>("/api/apps/subscribe_notification/developer/v1/notify", body, Some("access_token")).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invalid(builder: SubscribeMessageBuilder) -> String {
+        match builder.build() {
+            Err(DouyinError::InvalidParam(msg)) => msg,
+            other => panic!("expected InvalidParam, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn build_collects_fields() {
+        let msg = SubscribeMessage::builder("tpl", "open_id")
+            .field("thing1", "对局结束")
+            .field("time_2", "2024-01-01")
+            .page("pages/index")
+            .template_fields(&["time_2", "thing1"])
+            .build()
+            .unwrap();
+        assert_eq!(msg.data.len(), 2);
+        assert_eq!(msg.data["thing1"], "对局结束");
+        assert_eq!(msg.page.as_deref(), Some("pages/index"));
+    }
+
+    #[test]
+    fn build_rejects_bad_fields() {
+        assert!(invalid(SubscribeMessage::builder("", "open_id").field("thing1", "a")).contains("tpl_id"));
+        assert!(invalid(SubscribeMessage::builder("tpl", "").field("thing1", "a")).contains("open_id"));
+        assert!(invalid(SubscribeMessage::builder("tpl", "open_id")).contains("模板字段不能为空"));
+        for name in ["", "1thing", "_thing", "thing-1", "thing 1", "字段"] {
+            let msg = invalid(SubscribeMessage::builder("tpl", "open_id").field(name, "a"));
+            assert!(msg.contains("字段名不合法"), "{}", name);
+        }
+        let msg = invalid(SubscribeMessage::builder("tpl", "open_id").field("thing1", ""));
+        assert!(msg.contains("值不能为空"));
+        let msg = invalid(SubscribeMessage::builder("tpl", "open_id").field("thing1", "a").field("thing1", "b"));
+        assert!(msg.contains("字段重复: thing1"));
+    }
+
+    #[test]
+    fn build_checks_template_fields() {
+        let builder = SubscribeMessage::builder("tpl", "open_id").field("thing1", "a");
+        let msg = invalid(builder.clone().field("thing2", "b").template_fields(&["thing1"]));
+        assert!(msg.contains("模板中没有该字段: thing2"));
+        let msg = invalid(builder.clone().template_fields(&["thing1", "time2"]));
+        assert!(msg.contains("缺少模板字段: time2"));
+        assert!(builder.template_fields(&["thing1"]).build().is_ok());
+    }
+}
@@ -0,0 +1,454 @@
+/*
+* 抖音开放平台（open.douyin.com）的用户授权
+* client_key 和 client_secret 使用 SDK 的 appid 和 secret，用户的 token 保存在 UserTokenStore 中，按 appid 和 open_id 区分。
+*/
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{api::ApiResult, get_now_timestamp, telemetry, DouyinError, Secret, SDK};
+
+/**
+ * 用户授权的 token
+ */
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct UserToken {
+    pub open_id: String, // 授权用户的 open_id
+    pub scope: String, // 用户授权的权限，逗号分隔
+    pub access_token: Secret,
+    pub expires_in: u64, // access_token 的有效期（秒）
+    pub issued_at: u64, // 获取 access_token 时的时间戳（秒）
+    pub refresh_token: Secret,
+    pub refresh_expires_in: u64, // refresh_token 的有效期（秒）
+    pub refresh_issued_at: u64, // 获取 refresh_token 时的时间戳（秒）
+}
+
+impl UserToken {
+    /**
+     * 扣除安全余量后 access_token 是否还可以使用
+     */
+    pub fn access_token_valid(&self, now: u64, safety_margin: u64) -> bool {
+        self.issued_at.saturating_add(self.expires_in.saturating_sub(safety_margin.min(self.expires_in / 2))) > now
+    }
+
+    /**
+     * refresh_token 是否还可以使用
+     */
+    pub fn refresh_token_valid(&self, now: u64) -> bool {
+        self.refresh_issued_at.saturating_add(self.refresh_expires_in) > now
+    }
+
+    /**
+     * 是否授权了 scope
+     */
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scope.split(',').any(|item| item.trim() == scope)
+    }
+}
+
+/**
+ * 用户 token 的存储，与 TokenStore 类似，以 appid 和 open_id 为键
+ */
+#[async_trait]
+pub trait UserTokenStore: std::fmt::Debug + Send + Sync {
+    /**
+     * 读取 token，不存在或读取失败时返回 None
+     */
+    async fn load(&self, appid: &str, open_id: &str) -> Option<UserToken>;
+
+    /**
+     * 写入 token
+     */
+    async fn save(&self, appid: &str, token: &UserToken) -> Result<(), DouyinError>;
+
+    /**
+     * 删除 token，用户取消授权时调用
+     */
+    async fn remove(&self, appid: &str, open_id: &str) -> Result<(), DouyinError>;
+}
+
+/**
+ * 内存中的用户 token，进程重启后需要用户重新授权，生产环境建议实现 UserTokenStore 保存到数据库
+ */
+#[derive(Debug, Default)]
+pub struct MemoryUserTokenStore {
+    tokens: Mutex<HashMap<(String, String), UserToken>>,
+}
+
+impl MemoryUserTokenStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl UserTokenStore for MemoryUserTokenStore {
+    async fn load(&self, appid: &str, open_id: &str) -> Option<UserToken> {
+        self.tokens.lock().unwrap().get(&(appid.to_string(), open_id.to_string())).cloned()
+    }
+
+    async fn save(&self, appid: &str, token: &UserToken) -> Result<(), DouyinError> {
+        self.tokens.lock().unwrap().insert((appid.to_string(), token.open_id.clone()), token.clone());
+        Ok(())
+    }
+
+    async fn remove(&self, appid: &str, open_id: &str) -> Result<(), DouyinError> {
+        self.tokens.lock().unwrap().remove(&(appid.to_string(), open_id.to_string()));
+        Ok(())
+    }
+}
+
+/**
+ * 用户 access_token 失效的错误码，2190002 access_token 无效，2190008 access_token 过期
+ * 使用用户 token 的接口返回这些错误码时会刷新 token 后重试一次
+ */
+pub const USER_TOKEN_INVALID_CODES: &[i64] = &[2190002, 2190008];
+
+/**
+ * refresh_token 过期的错误码，刷新时返回这些错误码需要用户重新授权
+ */
+const REFRESH_TOKEN_EXPIRED_CODES: &[i64] = &[10010];
+
+/**
+ * user_request 的请求方式和 body
+ */
+pub(crate) enum UserPayload<'a> {
+    /// GET 请求
+    Get,
+    /// 没有 body 的 POST 请求
+    Post,
+    /// json 格式的 POST 请求
+    Json(&'a Value),
+    /// 表单格式的 POST 请求，access_token 和 open_id 同时放在表单中（/oauth/ 下的接口）
+    TokenForm,
+    /// multipart 格式上传视频，字段名为 video
+    Video { data: &'a [u8], file_name: &'a str },
+}
+
+/*
+* 开放平台接口的返回结构体，错误码在 data 中
+*/
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, bound(deserialize = "T: DeserializeOwned + Default"))]
+struct OAuthRes<T> {
+    data: OAuthData<T>,
+    message: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, bound(deserialize = "T: DeserializeOwned + Default"))]
+struct OAuthData<T> {
+    error_code: i64,
+    description: String,
+    #[serde(flatten)]
+    inner: T,
+}
+
+impl<T> ApiResult for OAuthRes<T> {
+    fn code(&self) -> i64 {
+        self.data.error_code
+    }
+    fn message(&self) -> String {
+        if self.data.description.is_empty() { self.message.clone() } else { self.data.description.clone() }
+    }
+}
+
+impl<T> OAuthRes<T> {
+    /**
+     * error_code 不为0时返回 DouyinError::Api
+     */
+    fn into_data(self) -> Result<T, DouyinError> {
+        Ok(self.into_result()?.data.inner)
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+struct OAuthTokenData {
+    open_id: String,
+    scope: String,
+    access_token: Secret,
+    expires_in: u64,
+    refresh_token: Secret,
+    refresh_expires_in: u64,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+struct RenewRefreshTokenData {
+    refresh_token: Secret,
+    expires_in: u64,
+}
+
+impl SDK {
+    /**
+     * 生成用户授权页的地址，scopes 例如 ["user_info", "fans.list"]，state 会原样带回 redirect_uri
+     */
+    pub fn oauth_authorize_url(&self, scopes: &[&str], redirect_uri: &str, state: &str) -> Result<String, DouyinError> {
+        let mut url = reqwest::Url::parse(&format!("{}/platform/oauth/connect/", self.open_base_url))
+            .map_err(|err| DouyinError::Config(format!("open_base_url不是合法的url: {}, {}", self.open_base_url, err)))?;
+        url.query_pairs_mut()
+            .append_pair("client_key", &self.appid)
+            .append_pair("response_type", "code")
+            .append_pair("scope", &scopes.join(","))
+            .append_pair("redirect_uri", redirect_uri)
+            .append_pair("state", state);
+        Ok(url.to_string())
+    }
+
+    /**
+     * 用授权回调中的 code 换取用户的 access_token，成功后写入 UserTokenStore
+     */
+    pub async fn oauth_access_token(&self, code: &str) -> Result<UserToken, DouyinError> {
+        let data: OAuthTokenData = self.oauth_request("/oauth/access_token/", &[
+            ("client_key", self.appid.as_str()),
+            ("client_secret", self.secret.expose()),
+            ("code", code),
+            ("grant_type", "authorization_code"),
+        ]).await?;
+        let now = get_now_timestamp(false);
+        let token = UserToken {
+            open_id: data.open_id,
+            scope: data.scope,
+            access_token: data.access_token,
+            expires_in: data.expires_in,
+            issued_at: now,
+            refresh_token: data.refresh_token,
+            refresh_expires_in: data.refresh_expires_in,
+            refresh_issued_at: now,
+        };
+        self.user_token_store.save(&self.appid, &token).await?;
+        Ok(token)
+    }
+
+    /**
+     * 用 refresh_token 刷新用户的 access_token
+     * 没有授权、refresh_token 已过期或平台返回 refresh_token 过期时返回 DouyinError::Unauthorized，需要用户重新授权
+     */
+    pub async fn oauth_refresh_token(&self, open_id: &str) -> Result<UserToken, DouyinError> {
+        let mut token = self.load_user_token(open_id).await?;
+        if !token.refresh_token_valid(get_now_timestamp(false)) {
+            return Err(DouyinError::Unauthorized(open_id.to_string()));
+        }
+        let data: OAuthTokenData = match self.oauth_request("/oauth/refresh_token/", &[
+            ("client_key", self.appid.as_str()),
+            ("grant_type", "refresh_token"),
+            ("refresh_token", token.refresh_token.expose()),
+        ]).await {
+            Err(DouyinError::Api { code, .. }) if REFRESH_TOKEN_EXPIRED_CODES.contains(&code) => {
+                return Err(DouyinError::Unauthorized(open_id.to_string()));
+            },
+            res => res?,
+        };
+        token.access_token = data.access_token;
+        token.expires_in = data.expires_in;
+        token.issued_at = get_now_timestamp(false);
+        if !data.scope.is_empty() {
+            token.scope = data.scope;
+        }
+        // 部分情况下刷新 access_token 时也会返回新的 refresh_token
+        if !data.refresh_token.is_empty() {
+            token.refresh_token = data.refresh_token;
+            token.refresh_expires_in = data.refresh_expires_in;
+            token.refresh_issued_at = token.issued_at;
+        }
+        self.user_token_store.save(&self.appid, &token).await?;
+        Ok(token)
+    }
+
+    /**
+     * 在 refresh_token 过期前获取新的 refresh_token，避免用户重新授权
+     */
+    pub async fn oauth_renew_refresh_token(&self, open_id: &str) -> Result<UserToken, DouyinError> {
+        let mut token = self.load_user_token(open_id).await?;
+        let data: RenewRefreshTokenData = self.oauth_request("/oauth/renew_refresh_token/", &[
+            ("client_key", self.appid.as_str()),
+            ("refresh_token", token.refresh_token.expose()),
+        ]).await?;
+        token.refresh_token = data.refresh_token;
+        token.refresh_expires_in = data.expires_in;
+        token.refresh_issued_at = get_now_timestamp(false);
+        self.user_token_store.save(&self.appid, &token).await?;
+        Ok(token)
+    }
+
+    /**
+     * 获取用户可用的 access_token，快过期时用 refresh_token 刷新
+     * 没有授权或 refresh_token 也过期时返回 DouyinError::Unauthorized，需要用户重新授权
+     */
+    pub async fn user_access_token(&self, open_id: &str) -> Result<String, DouyinError> {
+        let token = self.load_user_token(open_id).await?;
+        let now = get_now_timestamp(false);
+        if token.access_token_valid(now, self.token_safety_margin) {
+            return Ok(token.access_token.expose().to_string());
+        }
+        let token = self.oauth_refresh_token(open_id).await?;
+        Ok(token.access_token.expose().to_string())
+    }
+
+    /**
+     * 删除保存的用户 token
+     */
+    pub async fn remove_user_token(&self, open_id: &str) -> Result<(), DouyinError> {
+        self.user_token_store.remove(&self.appid, open_id).await
+    }
+
+    async fn load_user_token(&self, open_id: &str) -> Result<UserToken, DouyinError> {
+        self.user_token_store.load(&self.appid, open_id).await
+            .ok_or_else(|| DouyinError::Unauthorized(open_id.to_string()))
+    }
+
+    /**
+     * 以表单格式请求 oauth 接口，data.error_code 不为0时返回 DouyinError::Api
+     */
+    pub(crate) async fn oauth_request<T>(&self, path: &str, form: &[(&str, &str)]) -> Result<T, DouyinError>
+        where T: DeserializeOwned + Default
+    {
+        let span = telemetry::CallSpan::call(path);
+        span.trace(async {
+            let request = self.client.post(format!("{}{}", self.open_base_url, path))
+                .header("Accept", "application/json")
+                .form(form);
+            Ok(self.send(path, request).await?.json::<OAuthRes<T>>().await?)
+        }).await?.into_data()
+    }
+
+    /**
+     * 使用用户的 access_token 请求开放平台接口，open_id 会放到 query 中
+     * 返回 USER_TOKEN_INVALID_CODES 中的错误码时刷新 token 后重试一次
+     */
+    pub(crate) async fn user_request<T>(&self, open_id: &str, path: &str, query: &[(&str, &str)], payload: UserPayload<'_>) -> Result<T, DouyinError>
+        where T: DeserializeOwned + Default
+    {
+        let span = telemetry::CallSpan::call(path);
+        span.trace(async {
+            let mut retried = false;
+            loop {
+                let access_token = self.user_access_token(open_id).await?;
+                let url = format!("{}{}", self.open_base_url, path);
+                let mut request = match &payload {
+                    UserPayload::Get => self.client.get(url),
+                    UserPayload::Post => self.client.post(url),
+                    UserPayload::Json(body) => self.client.post(url).json(body),
+                    UserPayload::TokenForm => self.client.post(url).form(&[("access_token", access_token.as_str()), ("open_id", open_id)]),
+                    UserPayload::Video { data, file_name } => {
+                        let part = reqwest::multipart::Part::bytes(data.to_vec()).file_name(file_name.to_string());
+                        self.client.post(url).multipart(reqwest::multipart::Form::new().part("video", part))
+                    },
+                };
+                request = request
+                    .header("Accept", "application/json")
+                    .header("access-token", access_token)
+                    .query(&[("open_id", open_id)])
+                    .query(query);
+                let res = self.send(path, request).await?.json::<OAuthRes<T>>().await?;
+                if !retried && USER_TOKEN_INVALID_CODES.contains(&res.data.error_code) {
+                    retried = true;
+                    self.oauth_refresh_token(open_id).await?;
+                    continue;
+                }
+                return Ok(res);
+            }
+        }).await?.into_data()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::tests::stub_server;
+
+    fn token(issued_at: u64, refresh_issued_at: u64) -> UserToken {
+        UserToken {
+            open_id: "open_id".into(),
+            scope: "user_info, fans.list,video.list".into(),
+            access_token: "access".into(),
+            expires_in: 7200,
+            issued_at,
+            refresh_token: "refresh".into(),
+            refresh_expires_in: 86400,
+            refresh_issued_at,
+        }
+    }
+
+    async fn sdk(open_base_url: &str, token: Option<UserToken>) -> SDK {
+        let store = Arc::new(MemoryUserTokenStore::new());
+        if let Some(token) = token {
+            store.save("appid", &token).await.unwrap();
+        }
+        SDK::builder()
+            .appid("appid")
+            .secret("secret")
+            .open_base_url(open_base_url)
+            .token_store(Arc::new(crate::MemoryTokenStore::new()))
+            .user_token_store(store)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn token_validity_edges() {
+        let token = token(1000, 1000);
+        // 默认安全余量600秒
+        assert!(token.access_token_valid(7599, 600));
+        assert!(!token.access_token_valid(7600, 600));
+        // 安全余量超过有效期一半时按一半计算
+        assert!(token.access_token_valid(4599, 10000));
+        assert!(!token.access_token_valid(4600, 10000));
+        assert!(token.refresh_token_valid(87399));
+        assert!(!token.refresh_token_valid(87400));
+
+        let overflow = UserToken { issued_at: u64::MAX, refresh_issued_at: u64::MAX, ..token };
+        assert!(overflow.access_token_valid(u64::MAX - 1, 0));
+        assert!(overflow.refresh_token_valid(u64::MAX - 1));
+    }
+
+    #[test]
+    fn has_scope_matches_whole_items() {
+        let token = token(0, 0);
+        assert!(token.has_scope("user_info"));
+        assert!(token.has_scope("fans.list"));
+        assert!(token.has_scope("video.list"));
+        assert!(!token.has_scope("user"));
+        assert!(!token.has_scope("fans"));
+        assert!(!token.has_scope(""));
+    }
+
+    #[tokio::test]
+    async fn authorize_url_encodes_params() {
+        let sdk = sdk("https://open.douyin.com", None).await;
+        let url = sdk.oauth_authorize_url(&["user_info", "fans.list"], "https://example.com/cb?a=1&b=2", "s t&x").unwrap();
+        assert_eq!(url, "https://open.douyin.com/platform/oauth/connect/?client_key=appid&response_type=code\
+            &scope=user_info%2Cfans.list&redirect_uri=https%3A%2F%2Fexample.com%2Fcb%3Fa%3D1%26b%3D2&state=s+t%26x");
+    }
+
+    #[tokio::test]
+    async fn expired_refresh_token_is_unauthorized() {
+        let now = get_now_timestamp(false);
+        // 不会发出请求
+        let sdk = sdk("http://127.0.0.1:1", Some(token(now - 100_000, now - 100_000))).await;
+        assert!(matches!(sdk.user_access_token("open_id").await, Err(DouyinError::Unauthorized(_))));
+        assert!(matches!(sdk.oauth_refresh_token("open_id").await, Err(DouyinError::Unauthorized(_))));
+        assert!(matches!(sdk.user_access_token("other").await, Err(DouyinError::Unauthorized(_))));
+    }
+
+    #[tokio::test]
+    async fn retry_with_rejected_refresh_token_is_unauthorized() {
+        let now = get_now_timestamp(false);
+        let (base_url, requests) = stub_server(vec![
+            r#"{"data":{"error_code":2190008,"description":"access_token过期"}}"#,
+            r#"{"data":{"error_code":10010,"description":"refresh_token过期"}}"#,
+        ]).await;
+        let sdk = sdk(&base_url, Some(token(now, now))).await;
+        let res: Result<Value, DouyinError> = sdk.user_request("open_id", "/fans/list/", &[], UserPayload::Get).await;
+        assert!(matches!(res, Err(DouyinError::Unauthorized(open_id)) if open_id == "open_id"));
+        let requests = requests.lock().unwrap();
+        assert!(requests[0].starts_with("GET /fans/list/?open_id=open_id "));
+        assert!(requests[1].starts_with("POST /oauth/refresh_token/ "));
+        assert!(requests[1].contains("refresh_token=refresh"));
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::{future::BoxFuture, FutureExt, Stream};
+
+use crate::DouyinError;
+
+/**
+ * 默认每页数量，开放平台的列表接口大多最大为20
+ */
+pub const DEFAULT_PAGE_SIZE: u32 = 20;
+
+/**
+ * 游标分页接口返回的一页数据
+ */
+#[derive(Debug, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub cursor: i64, // 下一页的游标
+    pub has_more: bool, // 是否还有下一页
+}
+
+type FetchPage<'a, T> = Box<dyn FnMut(i64, u32) -> BoxFuture<'a, Result<Page<T>, DouyinError>> + Send + 'a>;
+
+/**
+ * 把 cursor/has_more 分页的接口转换为 Stream，按需请求下一页，逐个返回数据
+ * 出错时返回错误后结束；has_more 为 false、游标没有变化、游标为0或达到 max_items 时结束
+ */
+pub struct Paginator<'a, T> {
+    fetch: FetchPage<'a, T>,
+    page_size: u32,
+    max_items: Option<usize>,
+    cursor: i64,
+    buffer: VecDeque<T>,
+    pending: Option<BoxFuture<'a, Result<Page<T>, DouyinError>>>,
+    returned: usize,
+    done: bool,
+}
+
+impl<'a, T> Paginator<'a, T> {
+    /**
+     * fetch 的参数为游标和本页数量，第一页的游标默认为0
+     */
+    pub fn new<F, Fut>(mut fetch: F) -> Self
+        where F: FnMut(i64, u32) -> Fut + Send + 'a,
+              Fut: Future<Output = Result<Page<T>, DouyinError>> + Send + 'a
+    {
+        Paginator {
+            fetch: Box::new(move |cursor, count| fetch(cursor, count).boxed()),
+            page_size: DEFAULT_PAGE_SIZE,
+            max_items: None,
+            cursor: 0,
+            buffer: VecDeque::new(),
+            pending: None,
+            returned: 0,
+            done: false,
+        }
+    }
+
+    /**
+     * 每页数量
+     */
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /**
+     * 最多返回多少条数据，达到后不再请求下一页
+     */
+    pub fn max_items(mut self, max_items: usize) -> Self {
+        self.max_items = Some(max_items);
+        self
+    }
+
+    /**
+     * 从指定的游标开始，例如接着上次中断的位置继续
+     */
+    pub fn cursor(mut self, cursor: i64) -> Self {
+        self.cursor = cursor;
+        self
+    }
+
+    /**
+     * 下一页的游标，可以保存下来之后通过 cursor 继续
+     */
+    pub fn next_cursor(&self) -> i64 {
+        self.cursor
+    }
+}
+
+impl<T: Unpin> Stream for Paginator<'_, T> {
+    type Item = Result<T, DouyinError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            if this.max_items.is_some_and(|max_items| this.returned >= max_items) {
+                return Poll::Ready(None);
+            }
+            if let Some(item) = this.buffer.pop_front() {
+                this.returned += 1;
+                return Poll::Ready(Some(Ok(item)));
+            }
+            if this.done {
+                return Poll::Ready(None);
+            }
+            let pending = match &mut this.pending {
+                Some(pending) => pending,
+                None => {
+                    // 快到 max_items 时只请求剩下的数量
+                    let count = match this.max_items {
+                        Some(max_items) => this.page_size.min((max_items - this.returned).try_into().unwrap_or(u32::MAX)),
+                        None => this.page_size,
+                    };
+                    this.pending.insert((this.fetch)(this.cursor, count))
+                },
+            };
+            let res = match pending.as_mut().poll(cx) {
+                Poll::Ready(res) => res,
+                Poll::Pending => return Poll::Pending,
+            };
+            this.pending = None;
+            match res {
+                Ok(page) => {
+                    // 游标没有变化或回到0时继续请求会重复返回数据
+                    if !page.has_more || page.cursor == this.cursor || page.cursor == 0 {
+                        this.done = true;
+                    }
+                    this.cursor = page.cursor;
+                    this.buffer.extend(page.items);
+                },
+                Err(err) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(err)));
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    use futures::{executor::block_on, StreamExt};
+
+    type Calls = Arc<Mutex<Vec<(i64, u32)>>>;
+
+    /**
+     * 按顺序返回 pages，记录每次请求的游标和数量
+     */
+    fn stub(pages: Vec<Result<Page<u32>, DouyinError>>) -> (Paginator<'static, u32>, Calls) {
+        let calls = Calls::default();
+        let recorded = calls.clone();
+        let mut pages = pages.into_iter();
+        let paginator = Paginator::new(move |cursor, count| {
+            recorded.lock().unwrap().push((cursor, count));
+            let page = pages.next().expect("请求了多余的页");
+            async move { page }
+        });
+        (paginator, calls)
+    }
+
+    fn page(items: &[u32], cursor: i64, has_more: bool) -> Result<Page<u32>, DouyinError> {
+        Ok(Page { items: items.to_vec(), cursor, has_more })
+    }
+
+    fn collect(paginator: &mut Paginator<'_, u32>) -> Vec<Result<u32, DouyinError>> {
+        block_on(paginator.collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn stops_when_has_more_is_false() {
+        let (mut paginator, calls) = stub(vec![page(&[1, 2], 10, true), page(&[3], 20, false)]);
+        let items: Vec<u32> = collect(&mut paginator).into_iter().map(Result::unwrap).collect();
+        assert_eq!(items, [1, 2, 3]);
+        assert_eq!(*calls.lock().unwrap(), [(0, DEFAULT_PAGE_SIZE), (10, DEFAULT_PAGE_SIZE)]);
+        assert_eq!(paginator.next_cursor(), 20);
+    }
+
+    #[test]
+    fn stops_when_cursor_repeats_or_is_zero() {
+        let (mut paginator, calls) = stub(vec![page(&[1], 10, true), page(&[2], 10, true)]);
+        assert_eq!(collect(&mut paginator).len(), 2);
+        assert_eq!(calls.lock().unwrap().len(), 2);
+
+        let (mut paginator, calls) = stub(vec![page(&[], 0, true)]);
+        assert!(collect(&mut paginator).is_empty());
+        assert_eq!(calls.lock().unwrap().len(), 1);
+
+        let (mut paginator, calls) = stub(vec![page(&[1], 10, true), page(&[2], 0, true)]);
+        assert_eq!(collect(&mut paginator).len(), 2);
+        assert_eq!(calls.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn error_is_returned_once_then_stream_ends() {
+        let (mut paginator, calls) = stub(vec![
+            page(&[1], 10, true),
+            Err(DouyinError::Api { code: 1, msg: "error".into() }),
+            page(&[2], 20, false),
+        ]);
+        let items = collect(&mut paginator);
+        assert_eq!(items.len(), 2);
+        assert!(matches!(items[0], Ok(1)));
+        assert!(matches!(items[1], Err(DouyinError::Api { code: 1, .. })));
+        assert!(block_on(paginator.next()).is_none());
+        assert_eq!(calls.lock().unwrap().len(), 2);
+        assert_eq!(paginator.next_cursor(), 10);
+    }
+
+    #[test]
+    fn max_items_limits_last_page_size() {
+        let (paginator, calls) = stub(vec![page(&[1, 2], 10, true), page(&[3, 4], 20, true)]);
+        let mut paginator = paginator.page_size(2).max_items(3).cursor(5);
+        let items: Vec<u32> = collect(&mut paginator).into_iter().map(Result::unwrap).collect();
+        assert_eq!(items, [1, 2, 3]);
+        assert_eq!(*calls.lock().unwrap(), [(5, 2), (10, 1)]);
+    }
+}
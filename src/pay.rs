@@ -0,0 +1,416 @@
+/*
+* 担保支付
+* 请求使用支付 salt 做 MD5 签名，不需要 access_token。salt 在开发者后台->支付->担保支付设置中获取，
+* 通过 SdkBuilder::pay_salt 设置。
+*/
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{api::{ApiRes, ApiResult}, telemetry, DouyinError, SDK};
+
+pub mod callback;
+mod refund;
+mod settle;
+
+pub use refund::{CreateRefund, RefundCreated, RefundInfo};
+pub use settle::{CreateSettle, SettleCreated, SettleInfo, SettleParam};
+
+/**
+ * 金额，单位为分，接口中的金额都使用这个类型
+ * 不提供 + - 运算符，使用 checked_add/checked_sub，溢出或结果为负数时由调用方处理
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[serde(transparent)]
+pub struct Fen(pub u64);
+
+impl Fen {
+    pub fn to_yuan(self) -> Yuan {
+        Yuan(self.0)
+    }
+
+    /**
+     * 金额相加，溢出时返回 None
+     */
+    pub fn checked_add(self, rhs: Fen) -> Option<Fen> {
+        self.0.checked_add(rhs.0).map(Fen)
+    }
+
+    /**
+     * 金额相减，结果为负数时返回 None
+     */
+    pub fn checked_sub(self, rhs: Fen) -> Option<Fen> {
+        self.0.checked_sub(rhs.0).map(Fen)
+    }
+
+    /**
+     * 单价乘以数量，溢出时返回 None
+     */
+    pub fn checked_mul(self, quantity: u64) -> Option<Fen> {
+        self.0.checked_mul(quantity).map(Fen)
+    }
+
+    /**
+     * 金额求和，溢出时返回 None
+     */
+    pub fn checked_sum<I: IntoIterator<Item = Fen>>(iter: I) -> Option<Fen> {
+        iter.into_iter().try_fold(Fen(0), Fen::checked_add)
+    }
+}
+
+impl fmt::Display for Fen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}分", self.0)
+    }
+}
+
+/**
+ * 以元为单位的金额，只用于展示和输入，传给接口前转换为 Fen
+ * 内部按分保存，不使用浮点数，例如 "12.34".parse::<Yuan>()
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Yuan(u64);
+
+impl Yuan {
+    pub fn new(yuan: u64, fen: u8) -> Result<Self, DouyinError> {
+        if fen >= 100 {
+            return Err(DouyinError::InvalidParam(format!("分不能大于99: {}", fen)));
+        }
+        yuan.checked_mul(100)
+            .and_then(|value| value.checked_add(fen as u64))
+            .map(Yuan)
+            .ok_or_else(|| DouyinError::InvalidParam(format!("金额超出范围: {}元", yuan)))
+    }
+
+    pub fn to_fen(self) -> Fen {
+        Fen(self.0)
+    }
+
+    /**
+     * 金额相加，溢出时返回 None
+     */
+    pub fn checked_add(self, rhs: Yuan) -> Option<Yuan> {
+        self.0.checked_add(rhs.0).map(Yuan)
+    }
+}
+
+impl From<Yuan> for Fen {
+    fn from(yuan: Yuan) -> Fen {
+        yuan.to_fen()
+    }
+}
+
+impl From<Fen> for Yuan {
+    fn from(fen: Fen) -> Yuan {
+        fen.to_yuan()
+    }
+}
+
+impl FromStr for Yuan {
+    type Err = DouyinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || DouyinError::InvalidParam(format!("金额格式错误: {}", s));
+        let (yuan, fen) = s.trim().split_once('.').unwrap_or((s.trim(), ""));
+        if yuan.is_empty() || fen.len() > 2 || !yuan.chars().chain(fen.chars()).all(|c| c.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let yuan = yuan.parse::<u64>().map_err(|_| invalid())?;
+        let fen = format!("{:0<2}", fen).parse::<u8>().map_err(|_| invalid())?;
+        Yuan::new(yuan, fen)
+    }
+}
+
+impl fmt::Display for Yuan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{:02}", self.0 / 100, self.0 % 100)
+    }
+}
+
+/**
+ * 下单参数，可选字段直接赋值
+ */
+#[derive(Serialize, Debug, Clone)]
+pub struct CreateOrder {
+    pub out_order_no: String, // 开发者侧的订单号，同一小程序下不可重复
+    pub total_amount: Fen, // 支付金额
+    pub subject: String, // 商品描述，长度限制128
+    pub body: String, // 商品详情，长度限制128
+    pub valid_time: u64, // 订单过期时间（秒），最小5分钟，最大2天
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cp_extra: Option<String>, // 开发者自定义字段，回调原样回传
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notify_url: Option<String>, // 支付结果回调地址，不传时使用后台配置的地址
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thirdparty_id: Option<String>, // 服务商模式接入时必传，第三方平台服务商 id
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub store_uid: Option<String>, // 多门店模式下的门店 uid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disable_msg: Option<u8>, // 是否屏蔽支付完成后推送用户抖音消息，1 屏蔽
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub msg_page: Option<String>, // 支付完成后推送给用户的抖音消息跳转页面
+}
+
+impl CreateOrder {
+    pub fn new(out_order_no: &str, total_amount: Fen, subject: &str, body: &str, valid_time: u64) -> Self {
+        CreateOrder {
+            out_order_no: out_order_no.to_string(),
+            total_amount,
+            subject: subject.to_string(),
+            body: body.to_string(),
+            valid_time,
+            cp_extra: None,
+            notify_url: None,
+            thirdparty_id: None,
+            store_uid: None,
+            disable_msg: None,
+            msg_page: None,
+        }
+    }
+}
+
+/**
+ * 下单结果，order_id 和 order_token 传给小程序端的 tt.pay 拉起收银台
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct CreateOrderData {
+    pub order_id: String, // 抖音侧的订单号
+    pub order_token: String, // 签名后的订单信息
+}
+
+/**
+ * 订单支付状态
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OrderStatus {
+    /// 处理中
+    #[default]
+    Processing,
+    /// 支付成功
+    Success,
+    /// 支付失败
+    Fail,
+    /// 超时未支付
+    Timeout,
+    /// 新增的未知状态
+    #[serde(other)]
+    Unknown,
+}
+
+/**
+ * 退款状态
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RefundStatus {
+    /// 退款处理中
+    #[default]
+    Processing,
+    /// 退款成功
+    Success,
+    /// 退款失败
+    Fail,
+    /// 新增的未知状态
+    #[serde(other)]
+    Unknown,
+}
+
+/**
+ * 分账状态
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SettleStatus {
+    /// 分账处理中
+    #[default]
+    Processing,
+    /// 分账成功
+    Success,
+    /// 分账失败
+    Fail,
+    /// 新增的未知状态
+    #[serde(other)]
+    Unknown,
+}
+
+/**
+ * 查询订单的结果
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct QueryOrderData {
+    pub out_order_no: String, // 开发者侧的订单号
+    pub order_id: String, // 抖音侧的订单号
+    pub payment_info: PaymentInfo,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct PaymentInfo {
+    pub total_fee: Fen, // 支付金额
+    pub order_status: OrderStatus,
+    pub pay_time: String, // 支付时间，例如 2021-12-12 00:00:00
+    pub way: i64, // 支付渠道，1 微信 2 支付宝 10 抖音支付
+    pub channel_no: String, // 支付渠道侧的单号
+    pub channel_gateway_no: String, // 支付渠道侧的商户单号
+    pub seller_uid: String, // 收款商户号
+    pub item_id: String, // 视频 id
+    pub cp_extra: String, // 下单时传入的自定义字段
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+struct CreateOrderRes {
+    data: CreateOrderData,
+}
+
+/**
+ * 计算担保支付请求的签名
+ * 去掉 app_id、thirdparty_id、sign、other_settle_params 和空值，剩下参数的值加上 salt 按字典序排序后用 & 拼接，再取 MD5
+ */
+pub fn request_sign(params: &Value, salt: &str) -> String {
+    let mut values: Vec<String> = Vec::new();
+    if let Some(params) = params.as_object() {
+        for (key, value) in params {
+            if matches!(key.as_str(), "app_id" | "thirdparty_id" | "sign" | "other_settle_params") {
+                continue;
+            }
+            let value = match value {
+                Value::Null => continue,
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+            let value = value.trim();
+            let value = match value.strip_prefix('"').and_then(|value| value.strip_suffix('"')) {
+                Some(unquoted) => unquoted.trim(),
+                None => value,
+            };
+            if value.is_empty() || value == "null" {
+                continue;
+            }
+            values.push(value.to_string());
+        }
+    }
+    values.push(salt.to_string());
+    values.sort();
+    format!("{:x}", md5::compute(values.join("&")))
+}
+
+impl SDK {
+    /**
+     * 担保支付->预下单
+     */
+    pub async fn create_order(&self, order: &CreateOrder) -> Result<CreateOrderData, DouyinError> {
+        let body = serde_json::to_value(order)?;
+        let res = self.pay_request::<ApiRes<CreateOrderRes>>("/api/apps/ecpay/v1/create_order", body).await?;
+        Ok(res.data.data)
+    }
+
+    /**
+     * 担保支付->查询订单
+     */
+    pub async fn query_order(&self, out_order_no: &str, thirdparty_id: Option<&str>) -> Result<QueryOrderData, DouyinError> {
+        let mut body = json!({ "out_order_no": out_order_no });
+        if let Some(thirdparty_id) = thirdparty_id {
+            body["thirdparty_id"] = json!(thirdparty_id);
+        }
+        let res = self.pay_request::<ApiRes<QueryOrderData>>("/api/apps/ecpay/v1/query_order", body).await?;
+        Ok(res.data)
+    }
+
+    /**
+     * 补上 app_id 和签名后请求担保支付接口，错误码不为0时返回 DouyinError::Api
+     */
+    pub(crate) async fn pay_request<T>(&self, path: &str, mut body: Value) -> Result<T, DouyinError>
+        where T: DeserializeOwned + ApiResult
+    {
+        if self.pay_salt.is_empty() {
+            return Err(DouyinError::Config("未设置支付salt".into()));
+        }
+        body["app_id"] = json!(self.appid);
+        body["sign"] = json!(request_sign(&body, self.pay_salt.expose()));
+        let span = telemetry::CallSpan::call(path);
+        span.trace(async {
+            Ok(self.access_token_request(path, body).await?.json::<T>().await?)
+        }).await?.into_result()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_yuan() {
+        assert_eq!("12.34".parse::<Yuan>().unwrap().to_fen(), Fen(1234));
+        assert_eq!("12.3".parse::<Yuan>().unwrap().to_fen(), Fen(1230));
+        assert_eq!("12".parse::<Yuan>().unwrap().to_fen(), Fen(1200));
+        assert_eq!(" 0.05 ".parse::<Yuan>().unwrap().to_fen(), Fen(5));
+        for invalid in [".5", "1.234", "-1", " ", "", "1.2a", "+1", "1.-2"] {
+            assert!(matches!(invalid.parse::<Yuan>(), Err(DouyinError::InvalidParam(_))), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn parse_yuan_overflow() {
+        let max = format!("{}.{:02}", u64::MAX / 100, u64::MAX % 100);
+        assert_eq!(max.parse::<Yuan>().unwrap().to_fen(), Fen(u64::MAX));
+        for overflow in ["999999999999999999", "184467440737095516.16", "99999999999999999999"] {
+            assert!(matches!(overflow.parse::<Yuan>(), Err(DouyinError::InvalidParam(_))), "{:?}", overflow);
+        }
+        assert!(Yuan::new(u64::MAX / 100 + 1, 0).is_err());
+        assert!(Yuan::new(1, 100).is_err());
+    }
+
+    #[test]
+    fn display_yuan_round_trips() {
+        for (text, display) in [("12.34", "12.34"), ("12.3", "12.30"), ("12", "12.00"), ("0.05", "0.05"), ("0", "0.00")] {
+            let yuan = text.parse::<Yuan>().unwrap();
+            assert_eq!(yuan.to_string(), display);
+            assert_eq!(display.parse::<Yuan>().unwrap(), yuan);
+        }
+        assert_eq!(Fen(1234).to_yuan().to_string(), "12.34");
+        assert_eq!(Fen(7).to_string(), "7分");
+    }
+
+    #[test]
+    fn request_sign_skips_keys_and_empty_values() {
+        let params = json!({
+            "app_id": "appid",
+            "thirdparty_id": "thirdparty",
+            "sign": "old_sign",
+            "other_settle_params": "other",
+            "out_order_no": "order1",
+            "total_amount": 100,
+            "subject": "\"quoted\"",
+            "cp_extra": "  extra  ",
+            "disable_msg": 1,
+            "body": "",
+            "msg_page": "null",
+            "notify_url": null,
+            "store_uid": " \"\" ",
+        });
+        // 值按字符串排序，"1" < "100" < "extra"，与 key 的顺序无关
+        assert_eq!(request_sign(&params, "salt"), "50653e91405956494718b15da63b7e12");
+        assert_eq!(request_sign(&json!({}), "salt"), "ceb20772e0c9d240c75eb26b0e37abee");
+        assert_eq!(request_sign(&json!({ "app_id": "appid", "sign": "x" }), "salt"), "ceb20772e0c9d240c75eb26b0e37abee");
+        assert_ne!(request_sign(&params, "other_salt"), request_sign(&params, "salt"));
+    }
+
+    #[test]
+    fn checked_amounts() {
+        assert_eq!(Fen(1).checked_add(Fen(2)), Some(Fen(3)));
+        assert_eq!(Fen(u64::MAX).checked_add(Fen(1)), None);
+        assert_eq!(Fen(2).checked_sub(Fen(1)), Some(Fen(1)));
+        assert_eq!(Fen(1).checked_sub(Fen(2)), None);
+        assert_eq!(Fen(990).checked_mul(3), Some(Fen(2970)));
+        assert_eq!(Fen(u64::MAX).checked_mul(2), None);
+        assert_eq!(Fen::checked_sum([Fen(1), Fen(2), Fen(3)]), Some(Fen(6)));
+        assert_eq!(Fen::checked_sum([Fen(u64::MAX), Fen(1)]), None);
+        assert_eq!(Yuan::new(1, 50).unwrap().checked_add(Yuan::new(0, 50).unwrap()), Some(Yuan::new(2, 0).unwrap()));
+    }
+}
@@ -0,0 +1,310 @@
+/*
+* 担保支付的支付、退款、分账回调
+* 回调 body 中的 msg_signature 为 token、timestamp、nonce、msg 按字典序排序后拼接的 SHA1，
+* token 在开发者后台->支付->担保支付设置中配置，通过 SdkBuilder::pay_token 设置。
+*/
+use std::time::Duration;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::json;
+use sha1::{Digest, Sha1};
+
+use super::{Fen, RefundStatus, SettleStatus};
+use crate::{get_now_timestamp, sign, DouyinError, Secret, SDK};
+
+/**
+ * 回调请求的 body
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct CallbackBody {
+    pub timestamp: String,
+    pub nonce: String,
+    pub msg: String, // json 字符串，内容与 type 对应
+    #[serde(rename = "type")]
+    pub kind: String, // payment 支付成功，refund 退款，settle 分账
+    pub msg_signature: String,
+}
+
+/**
+ * 支付回调中的支付结果
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PaymentResult {
+    /// 支付成功
+    Success,
+    /// 支付取消
+    Cancel,
+    /// 新增的未知状态
+    #[default]
+    #[serde(other)]
+    Unknown,
+}
+
+/**
+ * 支付回调，cp_orderno、total_amount、status 为必填字段，缺少时解析失败，不会当作支付成功处理
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct PaymentNotify {
+    #[serde(default)]
+    pub appid: String,
+    pub cp_orderno: String, // 开发者侧的订单号
+    #[serde(default)]
+    pub cp_extra: String, // 下单时传入的自定义字段
+    #[serde(default)]
+    pub way: String, // 支付渠道，1 微信 2 支付宝 10 抖音支付
+    #[serde(default)]
+    pub channel_no: String, // 支付渠道侧的单号
+    #[serde(default)]
+    pub payment_order_no: String, // 支付渠道侧的商户单号
+    pub total_amount: Fen, // 支付金额
+    pub status: PaymentResult,
+    #[serde(default)]
+    pub seller_uid: String, // 收款商户号
+    #[serde(default)]
+    pub item_id: String, // 视频 id
+    #[serde(default)]
+    pub paid_at: u64, // 支付时间戳（秒）
+    #[serde(default)]
+    pub order_id: String, // 抖音侧的订单号
+}
+
+/**
+ * 退款回调，cp_refundno、refund_amount、status 为必填字段
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct RefundNotify {
+    #[serde(default)]
+    pub appid: String,
+    pub cp_refundno: String, // 开发者侧的退款单号
+    #[serde(default)]
+    pub cp_extra: String, // 退款时传入的自定义字段
+    pub status: RefundStatus,
+    pub refund_amount: Fen, // 退款金额
+    #[serde(default)]
+    pub is_all_settled: bool, // 是否已经全部分账
+    #[serde(default)]
+    pub refunded_at: u64, // 退款时间戳（秒）
+    #[serde(default)]
+    pub message: String, // 退款失败的原因
+    #[serde(default)]
+    pub order_id: String, // 抖音侧的订单号
+    #[serde(default)]
+    pub refund_no: String, // 抖音侧的退款单号
+}
+
+/**
+ * 分账回调，cp_settle_no、settle_amount、status 为必填字段
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct SettleNotify {
+    #[serde(default)]
+    pub appid: String,
+    pub cp_settle_no: String, // 开发者侧的分账单号
+    #[serde(default)]
+    pub cp_extra: String, // 分账时传入的自定义字段
+    pub status: SettleStatus,
+    #[serde(default)]
+    pub rake: Fen, // 手续费
+    #[serde(default)]
+    pub commission: Fen, // 佣金
+    #[serde(default)]
+    pub settle_detail: String, // 分账明细
+    #[serde(default)]
+    pub settled_at: u64, // 分账时间戳（秒）
+    #[serde(default)]
+    pub message: String, // 分账失败的原因
+    #[serde(default)]
+    pub order_id: String, // 抖音侧的订单号
+    #[serde(default)]
+    pub settle_no: String, // 抖音侧的分账单号
+    pub settle_amount: Fen, // 分账金额
+    #[serde(default)]
+    pub out_order_no: String, // 开发者侧的订单号
+    #[serde(default)]
+    pub is_auto_settle: bool, // 是否为平台自动分账
+}
+
+/**
+ * 验签后的回调通知
+ */
+#[derive(Debug, Clone)]
+pub enum Notification {
+    Payment(PaymentNotify),
+    Refund(RefundNotify),
+    Settle(SettleNotify),
+    /// 新增的回调类型，msg 为原始 json 字符串
+    Other { kind: String, msg: String },
+}
+
+/**
+ * 回调验签
+ */
+#[derive(Debug, Clone)]
+pub struct CallbackVerifier {
+    token: Secret,
+    max_age: Option<Duration>,
+}
+
+impl CallbackVerifier {
+    pub fn new(token: &str) -> Self {
+        CallbackVerifier { token: Secret::from(token), max_age: None }
+    }
+
+    /**
+     * 回调的 timestamp 与当前时间相差超过 max_age 时拒绝，避免截获的回调被重放
+     * 默认不校验，平台重试推送的回调会重新生成 timestamp
+     */
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /**
+     * 校验签名并解析回调 body，签名不一致时返回 DouyinError::SignMismatch，超过 max_age 时返回 DouyinError::CallbackExpired
+     */
+    pub fn verify(&self, body: &str) -> Result<Notification, DouyinError> {
+        let body = serde_json::from_str::<CallbackBody>(body)?;
+        self.verify_body(&body)?;
+        Ok(match body.kind.as_str() {
+            "payment" => Notification::Payment(parse_msg(&body.msg)?),
+            "refund" => Notification::Refund(parse_msg(&body.msg)?),
+            "settle" => Notification::Settle(parse_msg(&body.msg)?),
+            _ => Notification::Other { kind: body.kind, msg: body.msg },
+        })
+    }
+
+    /**
+     * 只校验签名和 timestamp，不解析 msg
+     */
+    pub fn verify_body(&self, body: &CallbackBody) -> Result<(), DouyinError> {
+        let expected = signature(self.token.expose(), &body.timestamp, &body.nonce, &body.msg);
+        if !sign::constant_time_eq(&expected, &body.msg_signature) {
+            return Err(DouyinError::SignMismatch);
+        }
+        if let Some(max_age) = self.max_age {
+            check_timestamp(&body.timestamp, get_now_timestamp(false), max_age)?;
+        }
+        Ok(())
+    }
+}
+
+/**
+ * timestamp（秒）与 now 相差不能超过 max_age，双向判断以容忍时钟偏差
+ */
+fn check_timestamp(timestamp: &str, now: u64, max_age: Duration) -> Result<(), DouyinError> {
+    let timestamp = timestamp.trim().parse::<u64>()
+        .map_err(|_| DouyinError::CallbackExpired(format!("timestamp格式错误: {}", timestamp)))?;
+    if now.abs_diff(timestamp) > max_age.as_secs() {
+        return Err(DouyinError::CallbackExpired(format!("timestamp: {}, 当前时间: {}", timestamp, now)));
+    }
+    Ok(())
+}
+
+fn parse_msg<T: DeserializeOwned>(msg: &str) -> Result<T, DouyinError> {
+    Ok(serde_json::from_str(msg)?)
+}
+
+/**
+ * 计算回调签名：token、timestamp、nonce、msg 按字典序排序后拼接，再取 SHA1 的十六进制
+ */
+pub fn signature(token: &str, timestamp: &str, nonce: &str, msg: &str) -> String {
+    let mut values = [token, timestamp, nonce, msg];
+    values.sort_unstable();
+    let digest = Sha1::digest(values.concat().as_bytes());
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/**
+ * 处理成功时返回给平台的 body
+ */
+pub fn success_response() -> String {
+    json!({ "err_no": 0, "err_tips": "success" }).to_string()
+}
+
+/**
+ * 处理失败时返回给平台的 body，平台会重试推送
+ */
+pub fn failure_response(err_tips: &str) -> String {
+    json!({ "err_no": 1, "err_tips": err_tips }).to_string()
+}
+
+impl SDK {
+    /**
+     * 使用 SdkBuilder::pay_token 设置的 token 校验并解析担保支付回调
+     * 设置了 SdkBuilder::pay_callback_max_age 时同时校验回调的 timestamp
+     */
+    pub fn verify_pay_callback(&self, body: &str) -> Result<Notification, DouyinError> {
+        if self.pay_token.is_empty() {
+            return Err(DouyinError::Config("未设置支付回调token".into()));
+        }
+        CallbackVerifier { token: self.pay_token.clone(), max_age: self.pay_callback_max_age }.verify(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn callback_body(token: &str, timestamp: &str, msg: &str) -> String {
+        json!({
+            "timestamp": timestamp,
+            "nonce": "nonce",
+            "msg": msg,
+            "type": "payment",
+            "msg_signature": signature(token, timestamp, "nonce", msg),
+        }).to_string()
+    }
+
+    #[test]
+    fn signature_sorts_and_hashes_values() {
+        assert_eq!(signature("token", "1600000000", "nonce", r#"{"a":1}"#), "eb1c7423ff6b5c43117021e3b21025d9c6b81e6b");
+        assert_eq!(signature(r#"{"a":1}"#, "nonce", "token", "1600000000"), "eb1c7423ff6b5c43117021e3b21025d9c6b81e6b");
+    }
+
+    #[test]
+    fn verify_payment() {
+        let msg = r#"{"cp_orderno":"o1","total_amount":100,"status":"SUCCESS"}"#;
+        let body = callback_body("token", "1600000000", msg);
+        match CallbackVerifier::new("token").verify(&body).unwrap() {
+            Notification::Payment(payment) => {
+                assert_eq!(payment.cp_orderno, "o1");
+                assert_eq!(payment.total_amount, Fen(100));
+                assert_eq!(payment.status, PaymentResult::Success);
+            },
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn tampered_callback_fails() {
+        let msg = r#"{"cp_orderno":"o1","total_amount":100,"status":"SUCCESS"}"#;
+        let body = callback_body("token", "1600000000", msg);
+        let tampered = body.replace(r#"\"total_amount\":100"#, r#"\"total_amount\":1"#);
+        assert_ne!(tampered, body);
+        assert!(matches!(CallbackVerifier::new("token").verify(&tampered), Err(DouyinError::SignMismatch)));
+        assert!(matches!(CallbackVerifier::new("other").verify(&body), Err(DouyinError::SignMismatch)));
+    }
+
+    #[test]
+    fn payment_without_status_or_amount_is_rejected() {
+        for msg in [r#"{"cp_orderno":"o1","total_amount":100}"#, r#"{"cp_orderno":"o1","status":"SUCCESS"}"#, r#"{"total_amount":100,"status":"SUCCESS"}"#] {
+            let body = callback_body("token", "1600000000", msg);
+            assert!(matches!(CallbackVerifier::new("token").verify(&body), Err(DouyinError::Json(_))), "{}", msg);
+        }
+    }
+
+    #[test]
+    fn max_age_rejects_stale_callbacks() {
+        let msg = r#"{"cp_orderno":"o1","total_amount":100,"status":"SUCCESS"}"#;
+        let verifier = CallbackVerifier::new("token").max_age(Duration::from_secs(300));
+        let now = get_now_timestamp(false);
+        assert!(verifier.verify(&callback_body("token", &now.to_string(), msg)).is_ok());
+        assert!(matches!(verifier.verify(&callback_body("token", &(now - 600).to_string(), msg)), Err(DouyinError::CallbackExpired(_))));
+        assert!(matches!(verifier.verify(&callback_body("token", &(now + 600).to_string(), msg)), Err(DouyinError::CallbackExpired(_))));
+        assert!(matches!(verifier.verify(&callback_body("token", "abc", msg)), Err(DouyinError::CallbackExpired(_))));
+        // 不设置 max_age 时不校验 timestamp
+        assert!(CallbackVerifier::new("token").verify(&callback_body("token", "1600000000", msg)).is_ok());
+    }
+}
@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::{Fen, RefundStatus};
+use crate::{api::ApiRes, DouyinError, SDK};
+
+/**
+ * 退款参数，可选字段直接赋值
+ */
+#[derive(Serialize, Debug, Clone)]
+pub struct CreateRefund {
+    pub out_order_no: String, // 开发者侧的订单号
+    pub out_refund_no: String, // 开发者侧的退款单号，同一笔退款重试时使用相同的单号
+    pub reason: String, // 退款原因
+    pub refund_amount: Fen, // 退款金额
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cp_extra: Option<String>, // 开发者自定义字段，回调原样回传
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notify_url: Option<String>, // 退款结果回调地址，不传时使用后台配置的地址
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thirdparty_id: Option<String>, // 服务商模式接入时必传
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disable_msg: Option<u8>, // 是否屏蔽退款后推送用户抖音消息，1 屏蔽
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub msg_page: Option<String>, // 退款后推送给用户的抖音消息跳转页面
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub all_settle: Option<u8>, // 订单已分账时是否允许退款，1 允许
+}
+
+impl CreateRefund {
+    pub fn new(out_order_no: &str, out_refund_no: &str, refund_amount: Fen, reason: &str) -> Self {
+        CreateRefund {
+            out_order_no: out_order_no.to_string(),
+            out_refund_no: out_refund_no.to_string(),
+            reason: reason.to_string(),
+            refund_amount,
+            cp_extra: None,
+            notify_url: None,
+            thirdparty_id: None,
+            disable_msg: None,
+            msg_page: None,
+            all_settle: None,
+        }
+    }
+}
+
+/**
+ * 发起退款的结果，退款是否成功以退款回调或 query_refund 为准
+ */
+#[derive(Serialize, Debug, Clone)]
+pub struct RefundCreated {
+    pub out_order_no: String, // 开发者侧的订单号
+    pub out_refund_no: String, // 开发者侧的退款单号
+    pub refund_no: String, // 抖音侧的退款单号
+    pub refund_amount: Fen, // 退款金额
+    pub duplicated: bool, // out_refund_no 之前已经发起过退款，本次没有重复退款
+}
+
+/**
+ * 查询退款的结果
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct RefundInfo {
+    #[serde(skip_deserializing)]
+    pub out_refund_no: String, // 开发者侧的退款单号
+    pub out_order_no: String, // 开发者侧的订单号，平台没有返回时为空
+    pub refund_no: String, // 抖音侧的退款单号
+    pub refund_amount: Fen, // 退款金额
+    pub refund_status: RefundStatus,
+    pub refunded_at: u64, // 退款时间戳（秒）
+    pub is_all_settled: bool, // 是否已经全部分账
+    pub cp_extra: String, // 退款时传入的自定义字段
+    pub msg: String, // 退款失败的原因
+}
+
+impl RefundInfo {
+    /**
+     * 是否为同一笔退款：已有的退款单存在、订单号和金额一致且没有失败
+     * 平台没有返回订单号时无法确认，视为不是同一笔
+     */
+    fn is_same_refund(&self, refund: &CreateRefund) -> bool {
+        !self.refund_no.is_empty()
+            && self.out_order_no == refund.out_order_no
+            && self.refund_amount == refund.refund_amount
+            && self.refund_status != RefundStatus::Fail
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+struct CreateRefundRes {
+    refund_no: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+struct QueryRefundRes {
+    #[serde(rename = "refundInfo")]
+    refund_info: RefundInfo,
+}
+
+impl SDK {
+    /**
+     * 担保支付->退款
+     * 同一个 out_refund_no 重复调用时不会重复退款：平台拒绝后查询已有的退款单，订单号、金额一致且没有失败时
+     * 返回 duplicated 为 true 的结果，否则返回平台的错误
+     */
+    pub async fn create_refund(&self, refund: &CreateRefund) -> Result<RefundCreated, DouyinError> {
+        let body = serde_json::to_value(refund)?;
+        let err = match self.pay_request::<ApiRes<CreateRefundRes>>("/api/apps/ecpay/v1/create_refund", body).await {
+            Ok(res) => {
+                return Ok(RefundCreated {
+                    out_order_no: refund.out_order_no.clone(),
+                    out_refund_no: refund.out_refund_no.clone(),
+                    refund_no: res.data.refund_no,
+                    refund_amount: refund.refund_amount,
+                    duplicated: false,
+                });
+            },
+            Err(err @ DouyinError::Api { .. }) => err,
+            Err(err) => return Err(err),
+        };
+        let existing = match self.query_refund(&refund.out_refund_no, refund.thirdparty_id.as_deref()).await {
+            Ok(existing) if existing.is_same_refund(refund) => existing,
+            _ => return Err(err),
+        };
+        Ok(RefundCreated {
+            out_order_no: refund.out_order_no.clone(),
+            out_refund_no: refund.out_refund_no.clone(),
+            refund_no: existing.refund_no,
+            refund_amount: existing.refund_amount,
+            duplicated: true,
+        })
+    }
+
+    /**
+     * 担保支付->查询退款
+     */
+    pub async fn query_refund(&self, out_refund_no: &str, thirdparty_id: Option<&str>) -> Result<RefundInfo, DouyinError> {
+        let mut body = json!({ "out_refund_no": out_refund_no });
+        if let Some(thirdparty_id) = thirdparty_id {
+            body["thirdparty_id"] = json!(thirdparty_id);
+        }
+        let res = self.pay_request::<ApiRes<QueryRefundRes>>("/api/apps/ecpay/v1/query_refund", body).await?;
+        Ok(RefundInfo {
+            out_refund_no: out_refund_no.to_string(),
+            ..res.data.refund_info
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn existing(out_order_no: &str, amount: u64, status: RefundStatus) -> RefundInfo {
+        RefundInfo {
+            out_order_no: out_order_no.into(),
+            refund_no: "refund_no".into(),
+            refund_amount: Fen(amount),
+            refund_status: status,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn same_refund_requires_order_amount_and_status() {
+        let refund = CreateRefund::new("order", "refund", Fen(100), "原因");
+        assert!(existing("order", 100, RefundStatus::Success).is_same_refund(&refund));
+        assert!(existing("order", 100, RefundStatus::Processing).is_same_refund(&refund));
+        assert!(!existing("order", 100, RefundStatus::Fail).is_same_refund(&refund));
+        assert!(!existing("order", 99, RefundStatus::Success).is_same_refund(&refund));
+        assert!(!existing("other", 100, RefundStatus::Success).is_same_refund(&refund));
+        assert!(!existing("", 100, RefundStatus::Success).is_same_refund(&refund));
+        assert!(!RefundInfo::default().is_same_refund(&CreateRefund::new("", "refund", Fen(0), "")));
+    }
+
+    #[test]
+    fn query_refund_response() {
+        let res: ApiRes<QueryRefundRes> = serde_json::from_str(r#"{"err_no":0,"err_tips":"","refundInfo":{
+            "out_order_no":"order","refund_no":"r1","refund_amount":100,"refund_status":"FAIL","msg":"余额不足"
+        }}"#).unwrap();
+        let info = res.data.refund_info;
+        assert_eq!(info.refund_status, RefundStatus::Fail);
+        assert!(!info.is_same_refund(&CreateRefund::new("order", "refund", Fen(100), "")));
+    }
+}
@@ -0,0 +1,198 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::{Fen, SettleStatus};
+use crate::{api::ApiRes, DouyinError, SDK};
+
+/**
+ * 分账方和分账金额
+ */
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SettleParam {
+    pub merchant_uid: String, // 分账方的商户号
+    pub amount: Fen, // 分账金额
+}
+
+/**
+ * 分账参数，可选字段直接赋值
+ */
+#[derive(Debug, Clone)]
+pub struct CreateSettle {
+    pub out_order_no: String, // 开发者侧的订单号
+    pub out_settle_no: String, // 开发者侧的分账单号，同一笔分账重试时使用相同的单号
+    pub settle_desc: String, // 分账描述
+    pub settle_params: Vec<SettleParam>, // 其他分账方，为空时全部分给收款商户
+    pub cp_extra: Option<String>, // 开发者自定义字段，回调原样回传
+    pub notify_url: Option<String>, // 分账结果回调地址，不传时使用后台配置的地址
+    pub thirdparty_id: Option<String>, // 服务商模式接入时必传
+    pub finish: Option<bool>, // 是否为最后一次分账
+    pub settle_amount: Option<Fen>, // 预期的分账金额，不会发送给平台；重复调用时与已有分账单的金额一致才视为同一笔分账
+}
+
+impl CreateSettle {
+    pub fn new(out_order_no: &str, out_settle_no: &str, settle_desc: &str) -> Self {
+        CreateSettle {
+            out_order_no: out_order_no.to_string(),
+            out_settle_no: out_settle_no.to_string(),
+            settle_desc: settle_desc.to_string(),
+            settle_params: Vec::new(),
+            cp_extra: None,
+            notify_url: None,
+            thirdparty_id: None,
+            finish: None,
+            settle_amount: None,
+        }
+    }
+}
+
+/**
+ * 发起分账的结果，分账是否成功以分账回调或 query_settle 为准
+ */
+#[derive(Serialize, Debug, Clone)]
+pub struct SettleCreated {
+    pub out_order_no: String, // 开发者侧的订单号
+    pub out_settle_no: String, // 开发者侧的分账单号
+    pub settle_no: String, // 抖音侧的分账单号
+    pub duplicated: bool, // out_settle_no 之前已经发起过分账，本次没有重复分账
+}
+
+/**
+ * 查询分账的结果
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct SettleInfo {
+    #[serde(skip_deserializing)]
+    pub out_settle_no: String, // 开发者侧的分账单号
+    pub out_order_no: String, // 开发者侧的订单号，平台没有返回时为空
+    pub settle_no: String, // 抖音侧的分账单号
+    pub settle_amount: Fen, // 分账金额
+    pub settle_status: SettleStatus,
+    pub settle_detail: String, // 分账明细
+    pub settled_at: u64, // 分账时间戳（秒）
+    pub rake: Fen, // 手续费
+    pub commission: Fen, // 佣金
+    pub cp_extra: String, // 分账时传入的自定义字段
+    pub msg: String, // 分账失败的原因
+}
+
+impl SettleInfo {
+    /**
+     * 是否为同一笔分账：已有的分账单存在、订单号和金额一致且没有失败
+     * 平台没有返回订单号或没有设置 CreateSettle::settle_amount 时无法确认，视为不是同一笔
+     */
+    fn is_same_settle(&self, settle: &CreateSettle) -> bool {
+        !self.settle_no.is_empty()
+            && self.out_order_no == settle.out_order_no
+            && settle.settle_amount == Some(self.settle_amount)
+            && self.settle_status != SettleStatus::Fail
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+struct CreateSettleRes {
+    settle_no: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+struct QuerySettleRes {
+    settle_info: SettleInfo,
+}
+
+impl SDK {
+    /**
+     * 担保支付->分账
+     * 同一个 out_settle_no 重复调用时不会重复分账：平台拒绝后查询已有的分账单，订单号、金额（settle_amount）一致且没有失败时
+     * 返回 duplicated 为 true 的结果，否则返回平台的错误
+     */
+    pub async fn create_settle(&self, settle: &CreateSettle) -> Result<SettleCreated, DouyinError> {
+        let mut body = json!({
+            "out_order_no": settle.out_order_no,
+            "out_settle_no": settle.out_settle_no,
+            "settle_desc": settle.settle_desc,
+        });
+        if !settle.settle_params.is_empty() {
+            // settle_params 为 json 字符串
+            body["settle_params"] = json!(serde_json::to_string(&settle.settle_params)?);
+        }
+        if let Some(cp_extra) = &settle.cp_extra {
+            body["cp_extra"] = json!(cp_extra);
+        }
+        if let Some(notify_url) = &settle.notify_url {
+            body["notify_url"] = json!(notify_url);
+        }
+        if let Some(thirdparty_id) = &settle.thirdparty_id {
+            body["thirdparty_id"] = json!(thirdparty_id);
+        }
+        if let Some(finish) = settle.finish {
+            body["finish"] = json!(finish.to_string());
+        }
+        let err = match self.pay_request::<ApiRes<CreateSettleRes>>("/api/apps/ecpay/v1/settle", body).await {
+            Ok(res) => {
+                return Ok(SettleCreated {
+                    out_order_no: settle.out_order_no.clone(),
+                    out_settle_no: settle.out_settle_no.clone(),
+                    settle_no: res.data.settle_no,
+                    duplicated: false,
+                });
+            },
+            Err(err @ DouyinError::Api { .. }) => err,
+            Err(err) => return Err(err),
+        };
+        match self.query_settle(&settle.out_settle_no, settle.thirdparty_id.as_deref()).await {
+            Ok(existing) if existing.is_same_settle(settle) => Ok(SettleCreated {
+                out_order_no: settle.out_order_no.clone(),
+                out_settle_no: settle.out_settle_no.clone(),
+                settle_no: existing.settle_no,
+                duplicated: true,
+            }),
+            _ => Err(err),
+        }
+    }
+
+    /**
+     * 担保支付->查询分账
+     */
+    pub async fn query_settle(&self, out_settle_no: &str, thirdparty_id: Option<&str>) -> Result<SettleInfo, DouyinError> {
+        let mut body = json!({ "out_settle_no": out_settle_no });
+        if let Some(thirdparty_id) = thirdparty_id {
+            body["thirdparty_id"] = json!(thirdparty_id);
+        }
+        let res = self.pay_request::<ApiRes<QuerySettleRes>>("/api/apps/ecpay/v1/query_settle", body).await?;
+        Ok(SettleInfo {
+            out_settle_no: out_settle_no.to_string(),
+            ..res.data.settle_info
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn existing(out_order_no: &str, amount: u64, status: SettleStatus) -> SettleInfo {
+        SettleInfo {
+            out_order_no: out_order_no.into(),
+            settle_no: "settle_no".into(),
+            settle_amount: Fen(amount),
+            settle_status: status,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn same_settle_requires_order_amount_and_status() {
+        let mut settle = CreateSettle::new("order", "settle", "分账");
+        // 没有设置预期金额时无法确认
+        assert!(!existing("order", 100, SettleStatus::Success).is_same_settle(&settle));
+        settle.settle_amount = Some(Fen(100));
+        assert!(existing("order", 100, SettleStatus::Success).is_same_settle(&settle));
+        assert!(existing("order", 100, SettleStatus::Processing).is_same_settle(&settle));
+        assert!(!existing("order", 100, SettleStatus::Fail).is_same_settle(&settle));
+        assert!(!existing("order", 50, SettleStatus::Success).is_same_settle(&settle));
+        assert!(!existing("other", 100, SettleStatus::Success).is_same_settle(&settle));
+        assert!(!SettleInfo { settle_no: String::new(), ..existing("order", 100, SettleStatus::Success) }.is_same_settle(&settle));
+    }
+}
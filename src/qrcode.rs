@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{api::ApiRes, get_now_timestamp, DouyinError, SDK};
+
+/**
+ * 二维码颜色，rgb 取值 0-255
+ */
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QrCodeColor {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+/**
+ * createQRCode 的参数，只设置需要的字段即可，其他字段使用平台默认值
+ */
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct QrCodeOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub appname: Option<String>, // 打开二维码的宿主 App，默认 toutiao，可选 douyin、pipixia、huoshan 等
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>, // 小程序页面路径，可以带 query，需要 urlencode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>, // 二维码宽度，单位 px，最小 280，最大 1280，默认 430
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_color: Option<QrCodeColor>, // 二维码线条颜色，默认黑色
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background: Option<QrCodeColor>, // 二维码背景颜色，默认白色
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub set_icon: Option<bool>, // 是否展示小程序/小游戏 icon，默认不展示
+}
+
+/**
+ * 二维码图片
+ */
+#[derive(Debug, Clone)]
+pub struct QrCode {
+    pub content_type: String, // 图片类型，例如 image/png
+    pub data: Vec<u8>, // 图片内容
+}
+
+/**
+ * schema 和链接的有效期
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkExpire {
+    /// 永久有效，url_link 不支持，使用平台默认有效期
+    Never,
+    /// 在指定的时间戳（秒）过期
+    At(u64),
+    /// 从现在开始的有效时长
+    After(Duration),
+}
+
+impl LinkExpire {
+    fn expire_time(&self) -> Option<u64> {
+        match self {
+            LinkExpire::Never => None,
+            LinkExpire::At(timestamp) => Some(*timestamp),
+            LinkExpire::After(duration) => Some(get_now_timestamp(false) + duration.as_secs()),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+struct SchemaRes {
+    data: SchemaData,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+struct SchemaData {
+    schema: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+struct UrlLinkRes {
+    url_link: String,
+}
+
+impl SDK {
+    /**
+     * 获取小程序/小游戏的二维码图片
+     */
+    pub async fn create_qrcode(&mut self, options: &QrCodeOptions) -> Result<QrCode, DouyinError> {
+        let body = serde_json::to_value(options)?;
+        let (content_type, data) = self.call_binary("/api/apps/qrcode", body, Some("access_token")).await?;
+        Ok(QrCode { content_type, data })
+    }
+
+    /**
+     * 生成打开小程序的 schema，path 为页面路径，query 为页面参数（json 字符串）
+     */
+    pub async fn generate_schema(&mut self, path: &str, query: Option<&str>, expire: LinkExpire) -> Result<String, DouyinError> {
+        let mut body = json!({
+            "ma_app_id": self.appid,
+            "path": path,
+            "query": query.unwrap_or_default(),
+        });
+        match expire.expire_time() {
+            Some(expire_time) => body["expire_time"] = json!(expire_time),
+            None => body["no_expire"] = json!(true),
+        }
+        let res = self.call_api::<ApiRes<SchemaRes>>("/api/apps/url/generate_schema", body, Some("access_token")).await?;
+        Ok(res.data.data.schema)
+    }
+
+    /**
+     * 生成打开小程序的链接，app_name 为宿主 App，例如 douyin
+     * url_link 不支持永久有效，LinkExpire::Never 时使用平台默认有效期
+     */
+    pub async fn generate_url_link(&mut self, app_name: &str, path: &str, query: Option<&str>, expire: LinkExpire) -> Result<String, DouyinError> {
+        let mut body = json!({
+            "ma_app_id": self.appid,
+            "app_name": app_name,
+            "path": path,
+            "query": query.unwrap_or_default(),
+        });
+        if let Some(expire_time) = expire.expire_time() {
+            body["expire_time"] = json!(expire_time);
+        }
+        let res = self.call_api::<ApiRes<UrlLinkRes>>("/api/apps/url_link/generate", body, Some("access_token")).await?;
+        Ok(res.data.url_link)
+    }
+}
@@ -0,0 +1,213 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use tokio::{sync::watch, task::JoinHandle};
+
+use crate::{get_now_timestamp, CachedToken, SDK};
+
+/**
+ * 后台刷新 access_token 的配置
+ */
+#[derive(Debug, Clone)]
+pub struct RefresherConfig {
+    pub refresh_ratio: f64, // 在有效期的多少比例时刷新，例如 0.5 表示过了一半有效期就刷新
+    pub min_interval: Duration, // 两次刷新的最小间隔
+    pub retry_interval: Duration, // 刷新失败后的重试间隔
+}
+
+impl Default for RefresherConfig {
+    fn default() -> Self {
+        RefresherConfig {
+            refresh_ratio: 0.5,
+            min_interval: Duration::from_secs(30),
+            retry_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RefresherConfig {
+    /**
+     * 刷新成功后等待多久再刷新：从签发时间开始计算，到有效期的 refresh_ratio 时刷新，不小于 min_interval
+     */
+    fn next_refresh(&self, token: Option<&CachedToken>, now: u64) -> Duration {
+        let refresh_at = token.map_or(0, |token| {
+            token.issued_at.saturating_add((token.expires_in as f64 * self.refresh_ratio.clamp(0.0, 1.0)) as u64)
+        });
+        Duration::from_secs(refresh_at.saturating_sub(now)).max(self.min_interval)
+    }
+}
+
+/**
+ * 后台刷新的健康状态
+ */
+#[derive(Debug, Clone, Default)]
+pub struct RefresherHealth {
+    pub last_success: Option<SystemTime>, // 最后一次成功的时间
+    pub last_error: Option<(SystemTime, String)>, // 最后一次失败的时间和原因
+    pub consecutive_failures: u32, // 连续失败次数，成功后清零
+}
+
+/**
+ * 后台刷新任务的句柄，drop 时停止刷新
+ */
+#[derive(Debug)]
+pub struct TokenRefresher {
+    receiver: watch::Receiver<Option<CachedToken>>,
+    health: Arc<Mutex<RefresherHealth>>,
+    handle: JoinHandle<()>,
+}
+
+impl TokenRefresher {
+    /**
+     * 订阅刷新后的 access_token
+     */
+    pub fn subscribe(&self) -> watch::Receiver<Option<CachedToken>> {
+        self.receiver.clone()
+    }
+
+    /**
+     * 当前的 access_token，还没有刷新成功过时为 None
+     */
+    pub fn token(&self) -> Option<CachedToken> {
+        self.receiver.borrow().clone()
+    }
+
+    pub fn health(&self) -> RefresherHealth {
+        self.health.lock().unwrap().clone()
+    }
+
+    /**
+     * 停止后台刷新
+     */
+    pub fn stop(&self) {
+        self.handle.abort();
+    }
+}
+
+impl Drop for TokenRefresher {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+impl SDK {
+    /**
+     * 启动后台任务定时刷新 access_token，需要在 tokio 运行时中调用
+     * 刷新后的 token 会写入缓存并通过 watch channel 发布，当前 SDK 以及之后 clone 出来的 SDK 都会优先使用它，
+     * 请求时不再需要等待 token 接口。
+     */
+    pub fn spawn_token_refresher(&mut self, config: RefresherConfig) -> TokenRefresher {
+        let (sender, receiver) = watch::channel(None);
+        let health = Arc::new(Mutex::new(RefresherHealth::default()));
+        let mut sdk = self.clone();
+        sdk.token_rx = None;
+        let task_health = health.clone();
+        let handle = tokio::spawn(async move {
+            // 第一次优先使用缓存，之后每次都重新请求
+            let mut force = false;
+            loop {
+                let res = if force {
+                    sdk.refresh_access_token().await
+                } else {
+                    sdk.get_access_token().await.map(|_| ())
+                };
+                let wait = match res {
+                    Ok(()) => {
+                        let _ = sender.send(sdk.token.clone());
+                        let mut health = task_health.lock().unwrap();
+                        health.last_success = Some(SystemTime::now());
+                        health.consecutive_failures = 0;
+                        force = true;
+                        config.next_refresh(sdk.token.as_ref(), get_now_timestamp(false))
+                    },
+                    Err(err) => {
+                        let mut health = task_health.lock().unwrap();
+                        health.last_error = Some((SystemTime::now(), err.to_string()));
+                        health.consecutive_failures += 1;
+                        config.retry_interval
+                    },
+                };
+                tokio::time::sleep(wait).await;
+            }
+        });
+        self.token_rx = Some(receiver.clone());
+        TokenRefresher { receiver, health, handle }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tests::offline_sdk, DouyinError, MemoryTokenStore, TokenStore};
+
+    fn token(issued_at: u64, expires_in: u64) -> CachedToken {
+        CachedToken { issued_at, ..CachedToken::new("appid", "token1".into(), expires_in) }
+    }
+
+    #[test]
+    fn next_refresh_uses_ratio_of_expires_in() {
+        let config = RefresherConfig::default();
+        assert_eq!(config.next_refresh(Some(&token(1000, 7200)), 1000), Duration::from_secs(3600));
+        assert_eq!(config.next_refresh(Some(&token(1000, 7200)), 4000), Duration::from_secs(600));
+        // 已经过了刷新时间或没有 token 时按最小间隔
+        assert_eq!(config.next_refresh(Some(&token(1000, 7200)), 9000), config.min_interval);
+        assert_eq!(config.next_refresh(None, 1000), config.min_interval);
+
+        let config = RefresherConfig { refresh_ratio: 2.0, ..Default::default() };
+        assert_eq!(config.next_refresh(Some(&token(1000, 7200)), 1000), Duration::from_secs(7200));
+        let config = RefresherConfig { refresh_ratio: 0.0, ..Default::default() };
+        assert_eq!(config.next_refresh(Some(&token(1000, 7200)), 1000), config.min_interval);
+        assert_eq!(config.next_refresh(Some(&token(u64::MAX, u64::MAX)), 0), Duration::from_secs(u64::MAX));
+    }
+
+    #[tokio::test]
+    async fn get_access_token_uses_published_token() {
+        let store = Arc::new(MemoryTokenStore::new());
+        let mut sdk = offline_sdk(store.clone());
+        let (sender, receiver) = watch::channel(None);
+        sdk.token_rx = Some(receiver);
+        sender.send(Some(CachedToken::new("appid", "token2".into(), 7200))).unwrap();
+        assert_eq!(sdk.get_access_token().await.unwrap(), "token2");
+        // 没有读缓存，也没有请求 token 接口
+        assert!(store.load("appid").await.is_none());
+
+        // 发布的 token 已过期时忽略
+        let mut sdk = offline_sdk(store);
+        let (sender, receiver) = watch::channel(None);
+        sdk.token_rx = Some(receiver);
+        sender.send(Some(token(0, 7200))).unwrap();
+        assert!(matches!(sdk.get_access_token().await, Err(DouyinError::Http(_))));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn refresher_publishes_token_and_counts_failures() {
+        let store = Arc::new(MemoryTokenStore::new());
+        store.save("appid", &CachedToken::new("appid", "token1".into(), 7200)).await.unwrap();
+        let mut sdk = offline_sdk(store.clone());
+        let refresher = sdk.spawn_token_refresher(RefresherConfig::default());
+
+        refresher.subscribe().changed().await.unwrap();
+        assert_eq!(refresher.token().unwrap().access_token.expose(), "token1");
+        let health = refresher.health();
+        assert!(health.last_success.is_some());
+        assert_eq!(health.consecutive_failures, 0);
+
+        // 缓存被清除后，SDK 仍然使用后台发布的 token
+        store.remove("appid").await.unwrap();
+        assert_eq!(sdk.get_access_token().await.unwrap(), "token1");
+
+        // 到刷新时间后强制请求 token 接口，失败后按 retry_interval 重试
+        for _ in 0..100_000 {
+            if refresher.health().consecutive_failures >= 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+        let health = refresher.health();
+        assert!(health.consecutive_failures >= 2);
+        assert!(health.last_error.is_some());
+        assert!(health.last_success.is_some());
+        // 刷新失败不会撤回已经发布的 token
+        assert_eq!(refresher.token().unwrap().access_token.expose(), "token1");
+    }
+}
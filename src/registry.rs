@@ -0,0 +1,298 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+
+use serde::de::DeserializeOwned;
+use tokio::sync::Mutex;
+
+use crate::{push_sign, sign, DouyinError, FileTokenStore, LiveOpenRes, RetryPolicy, Secret, TokenStore, SDK};
+
+/**
+ * 注册到 SdkRegistry 的应用信息
+ */
+#[derive(Debug, Clone, Default)]
+pub struct AppCredentials {
+    pub appid: String,
+    pub secret: Secret,
+    pub app_private_key: Option<Secret>,
+    pub pkcs_type: Option<sign::PkcsType>,
+}
+
+/**
+ * 多应用管理，一个进程内管理多个 appid
+ * 所有 SDK 共享同一个 http 客户端和 access_token 缓存，SDK 在第一次使用时才会构造。
+ * 同时记录直播间和 appid 的对应关系，用来把推送回调分发到对应的应用。
+ */
+#[derive(Debug)]
+pub struct SdkRegistry {
+    client: reqwest::Client,
+    token_store: Arc<dyn TokenStore>,
+    base_url: Option<String>,
+    access_base_url: Option<String>,
+    open_base_url: Option<String>,
+    retry_policy: RetryPolicy,
+
+    apps: RwLock<HashMap<String, AppCredentials>>, // appid -> 应用信息
+    sdks: Mutex<HashMap<String, Arc<Mutex<SDK>>>>, // appid -> 已构造的 SDK
+    rooms: RwLock<HashMap<String, String>>, // roomid -> appid
+}
+
+impl Default for SdkRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SdkRegistry {
+    /**
+     * 默认使用按 appid 区分的文件缓存，避免多个应用写同一个 douyin_access_token.json
+     */
+    pub fn new() -> Self {
+        SdkRegistry {
+            client: reqwest::Client::new(),
+            token_store: Arc::new(FileTokenStore::per_app()),
+            base_url: None,
+            access_base_url: None,
+            open_base_url: None,
+            retry_policy: RetryPolicy::default(),
+            apps: RwLock::new(HashMap::new()),
+            sdks: Mutex::new(HashMap::new()),
+            rooms: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    pub fn token_store(mut self, token_store: Arc<dyn TokenStore>) -> Self {
+        self.token_store = token_store;
+        self
+    }
+
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        self.base_url = Some(base_url.to_string());
+        self
+    }
+
+    pub fn access_base_url(mut self, access_base_url: &str) -> Self {
+        self.access_base_url = Some(access_base_url.to_string());
+        self
+    }
+
+    pub fn open_base_url(mut self, open_base_url: &str) -> Self {
+        self.open_base_url = Some(open_base_url.to_string());
+        self
+    }
+
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /**
+     * 注册应用，重复注册会替换旧的配置，已构造的 SDK 会在下次获取时重新构造
+     */
+    pub async fn register(&self, app: AppCredentials) {
+        let appid = app.appid.clone();
+        self.apps.write().unwrap().insert(appid.clone(), app);
+        self.sdks.lock().await.remove(&appid);
+    }
+
+    /**
+     * 移除应用，同时解除该应用绑定的直播间
+     */
+    pub async fn unregister(&self, appid: &str) {
+        self.apps.write().unwrap().remove(appid);
+        self.sdks.lock().await.remove(appid);
+        self.rooms.write().unwrap().retain(|_, bind_appid| bind_appid != appid);
+    }
+
+    /**
+     * 已注册的 appid 列表
+     */
+    pub fn appids(&self) -> Vec<String> {
+        self.apps.read().unwrap().keys().cloned().collect()
+    }
+
+    /**
+     * 获取 appid 对应的 SDK，第一次获取时构造
+     * 不要在持有锁的同时等待接口请求，否则同一个 appid 的请求会排队，可以参考 task 复制一份再调用
+     */
+    pub async fn get(&self, appid: &str) -> Result<Arc<Mutex<SDK>>, DouyinError> {
+        let mut sdks = self.sdks.lock().await;
+        if let Some(sdk) = sdks.get(appid) {
+            return Ok(sdk.clone());
+        }
+        let app = self.apps.read().unwrap().get(appid).cloned()
+            .ok_or_else(|| DouyinError::UnknownApp(appid.to_string()))?;
+        let sdk = Arc::new(Mutex::new(self.build_sdk(app)?));
+        sdks.insert(appid.to_string(), sdk.clone());
+        Ok(sdk)
+    }
+
+    fn build_sdk(&self, app: AppCredentials) -> Result<SDK, DouyinError> {
+        let mut builder = SDK::builder()
+            .appid(&app.appid)
+            .secret(app.secret.expose())
+            .client(self.client.clone())
+            .token_store(self.token_store.clone())
+            .retry_policy(self.retry_policy.clone());
+        if let Some(key) = &app.app_private_key {
+            builder = builder.app_private_key(key.expose());
+        }
+        if let Some(pkcs_type) = app.pkcs_type {
+            builder = builder.pkcs_type(pkcs_type);
+        }
+        if let Some(base_url) = &self.base_url {
+            builder = builder.base_url(base_url);
+        }
+        if let Some(access_base_url) = &self.access_base_url {
+            builder = builder.access_base_url(access_base_url);
+        }
+        if let Some(open_base_url) = &self.open_base_url {
+            builder = builder.open_base_url(open_base_url);
+        }
+        builder.build()
+    }
+
+    /**
+     * 绑定直播间和 appid，推送回调按直播间分发
+     */
+    pub fn bind_room(&self, roomid: &str, appid: &str) {
+        self.rooms.write().unwrap().insert(roomid.to_string(), appid.to_string());
+    }
+
+    pub fn unbind_room(&self, roomid: &str) {
+        self.rooms.write().unwrap().remove(roomid);
+    }
+
+    /**
+     * 直播间绑定的 appid
+     */
+    pub fn room_appid(&self, roomid: &str) -> Option<String> {
+        self.rooms.read().unwrap().get(roomid).cloned()
+    }
+
+    /**
+     * 调用 appid 对应 SDK 的 task，启动成功后自动绑定直播间，停止后解除绑定
+     */
+    pub async fn task<T>(&self, appid: &str, task: &str, roomid: &str, msg_type: &str) -> Result<LiveOpenRes<T>, DouyinError>
+        where T: DeserializeOwned
+    {
+        let shared = self.get(appid).await?;
+        // 不在锁内等待网络请求，复制一份 SDK 发请求，同一个 appid 的请求可以并发
+        let mut sdk = shared.lock().await.clone();
+        let res = sdk.task::<T>(task, roomid, appid, msg_type).await;
+        // 把请求过程中刷新或作废的 token 同步回共享的 SDK
+        shared.lock().await.sync_token_from(&sdk);
+        let res = res?;
+        if res.err_no == 0 {
+            match task {
+                "start" => self.bind_room(roomid, appid),
+                "stop" => self.unbind_room(roomid),
+                _ => {},
+            }
+        }
+        Ok(res)
+    }
+
+    /**
+     * 推送回调分发：按 x-roomid 找到对应的应用，并用该应用的 secret 校验签名
+     * map 与 SDK::verify_sign 的参数一致，signature 为请求头中的 x-signature
+     * 校验通过后返回 appid
+     */
+    pub fn route_push(&self, map: BTreeMap<&str, &str>, body: &str, signature: &str) -> Result<String, DouyinError> {
+        let roomid = map.get("x-roomid").copied().unwrap_or_default();
+        let appid = self.room_appid(roomid)
+            .ok_or_else(|| DouyinError::UnknownApp(format!("roomid: {}", roomid)))?;
+        let secret = self.apps.read().unwrap().get(&appid)
+            .map(|app| app.secret.clone())
+            .ok_or_else(|| DouyinError::UnknownApp(appid.clone()))?;
+        if !sign::constant_time_eq(&push_sign(map, body, secret.expose()), signature) {
+            return Err(DouyinError::SignMismatch);
+        }
+        Ok(appid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryTokenStore;
+
+    async fn registry() -> SdkRegistry {
+        let registry = SdkRegistry::new().token_store(Arc::new(MemoryTokenStore::new()));
+        for (appid, secret) in [("app1", "secret1"), ("app2", "secret2")] {
+            registry.register(AppCredentials { appid: appid.into(), secret: secret.into(), ..Default::default() }).await;
+        }
+        registry
+    }
+
+    fn headers(roomid: &str) -> BTreeMap<&str, &str> {
+        BTreeMap::from([("x-roomid", roomid), ("x-msg-type", "live_comment"), ("x-nonce-str", "nonce"), ("x-timestamp", "1700000000")])
+    }
+
+    const BODY: &str = r#"[{"msg_id":"1","content":"hi"}]"#;
+
+    #[tokio::test]
+    async fn route_push_uses_the_bound_app_secret() {
+        let registry = registry().await;
+        registry.bind_room("room1", "app1");
+        registry.bind_room("room2", "app2");
+
+        let signature = push_sign(headers("room1"), BODY, "secret1");
+        assert_eq!(registry.route_push(headers("room1"), BODY, &signature).unwrap(), "app1");
+        let signature = push_sign(headers("room2"), BODY, "secret2");
+        assert_eq!(registry.route_push(headers("room2"), BODY, &signature).unwrap(), "app2");
+
+        // 用其他应用的 secret 签名
+        let signature = push_sign(headers("room1"), BODY, "secret2");
+        assert!(matches!(registry.route_push(headers("room1"), BODY, &signature), Err(DouyinError::SignMismatch)));
+        let signature = push_sign(headers("room1"), BODY, "secret1");
+        assert!(matches!(registry.route_push(headers("room1"), "[]", &signature), Err(DouyinError::SignMismatch)));
+    }
+
+    #[tokio::test]
+    async fn route_push_rejects_unknown_rooms_and_apps() {
+        let registry = registry().await;
+        let signature = push_sign(headers("room1"), BODY, "secret1");
+        assert!(matches!(registry.route_push(headers("room1"), BODY, &signature), Err(DouyinError::UnknownApp(_))));
+        assert!(matches!(registry.route_push(BTreeMap::new(), BODY, &signature), Err(DouyinError::UnknownApp(_))));
+
+        registry.bind_room("room1", "app1");
+        assert!(registry.route_push(headers("room1"), BODY, &signature).is_ok());
+        registry.unbind_room("room1");
+        assert!(matches!(registry.route_push(headers("room1"), BODY, &signature), Err(DouyinError::UnknownApp(_))));
+
+        // 绑定到未注册的应用
+        registry.bind_room("room1", "app3");
+        assert!(matches!(registry.route_push(headers("room1"), BODY, &signature), Err(DouyinError::UnknownApp(_))));
+    }
+
+    #[tokio::test]
+    async fn unregister_unbinds_rooms() {
+        let registry = registry().await;
+        registry.bind_room("room1", "app1");
+        registry.bind_room("room2", "app2");
+        registry.unregister("app1").await;
+
+        assert_eq!(registry.room_appid("room1"), None);
+        assert_eq!(registry.room_appid("room2").as_deref(), Some("app2"));
+        assert_eq!(registry.appids(), ["app2"]);
+        assert!(matches!(registry.get("app1").await, Err(DouyinError::UnknownApp(_))));
+        let signature = push_sign(headers("room1"), BODY, "secret1");
+        assert!(matches!(registry.route_push(headers("room1"), BODY, &signature), Err(DouyinError::UnknownApp(_))));
+    }
+
+    #[tokio::test]
+    async fn get_builds_once_and_register_replaces() {
+        let registry = registry().await;
+        let sdk = registry.get("app1").await.unwrap();
+        assert!(Arc::ptr_eq(&sdk, &registry.get("app1").await.unwrap()));
+        assert_eq!(sdk.lock().await.secret.expose(), "secret1");
+
+        registry.register(AppCredentials { appid: "app1".into(), secret: "secret3".into(), ..Default::default() }).await;
+        assert_eq!(registry.get("app1").await.unwrap().lock().await.secret.expose(), "secret3");
+    }
+}
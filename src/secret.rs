@@ -0,0 +1,115 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use zeroize::Zeroizing;
+
+/**
+ * 保存 secret、私钥、access_token 等敏感字符串
+ * Debug 输出为 `***`，避免日志中泄露；drop 时清零内存。
+ * 需要原文时调用 expose()，序列化时输出原文（用于写入缓存文件）。
+ */
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret(Zeroizing<String>);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Secret(Zeroizing::new(value.into()))
+    }
+
+    /**
+     * 获取原文
+     */
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("***")
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Secret::new(value)
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Secret::new(value)
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.expose())
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Secret::new)
+    }
+}
+
+/**
+ * 解析后的私钥，Debug 输出为 `***`（RsaPrivateKey 自身 drop 时会清零）
+ */
+#[derive(Clone)]
+pub(crate) struct SecretKey(pub(crate) rsa::RsaPrivateKey);
+
+impl std::fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("***")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{tests::stub_server, AccessTokenResData, CachedToken, DouyinConfig, MemoryTokenStore, TokenStore, SDK};
+
+    const PRIVATE_KEY: &str = include_str!("../examples/private_key.pem");
+
+    fn assert_redacted(debug: &str) {
+        assert!(debug.contains("***"), "{}", debug);
+        for plaintext in ["appsecret", "token_plaintext", "PRIVATE KEY"] {
+            assert!(!debug.contains(plaintext), "{} in {}", plaintext, debug);
+        }
+        // 私钥的任意一行都不能出现
+        for line in PRIVATE_KEY.lines().filter(|line| !line.starts_with("-----")) {
+            assert!(!debug.contains(line.trim()), "{}", debug);
+        }
+    }
+
+    #[test]
+    fn config_and_token_debug_is_redacted() {
+        let config = DouyinConfig { appid: "appid", secret: "appsecret", app_private_key: PRIVATE_KEY, ..Default::default() };
+        assert_redacted(&format!("{:?}", config));
+        let data = AccessTokenResData { access_token: "token_plaintext".into(), expires_in: 7200 };
+        assert_redacted(&format!("{:?}", data));
+        assert_redacted(&format!("{:?}", CachedToken::new("appid", "token_plaintext".into(), 7200)));
+    }
+
+    #[tokio::test]
+    async fn sdk_and_builder_debug_is_redacted() {
+        let builder = SDK::builder().appid("appid").secret("appsecret").app_private_key(PRIVATE_KEY);
+        assert_redacted(&format!("{:?}", builder));
+
+        let store = Arc::new(MemoryTokenStore::new());
+        store.save("appid", &CachedToken::new("appid", "token_plaintext".into(), 7200)).await.unwrap();
+        let mut sdk = builder.token_store(store).build().unwrap();
+        // 读取 access_token 并解析私钥后再检查
+        let (base_url, _) = stub_server(vec!["{}"]).await;
+        sdk.sign_request_with(reqwest::Method::GET, &base_url, "/path", &[], None).await.unwrap();
+        assert!(sdk.private_key.is_some());
+        assert_eq!(sdk.access_token.expose(), "token_plaintext");
+        assert_redacted(&format!("{:?}", sdk));
+        assert_redacted(&format!("{:#?}", sdk));
+    }
+}
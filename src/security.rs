@@ -0,0 +1,252 @@
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{api::{ApiResult, BaseRes}, DouyinError, SDK};
+
+/**
+ * 每次请求最多检测的文本数量，check_texts 会按这个数量分批请求
+ */
+pub const TEXT_BATCH_SIZE: usize = 10;
+
+/*
+* 内容安全检测的返回结构体
+*/
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+struct TextAntidirtRes {
+    #[serde(flatten)]
+    base: BaseRes,
+    data: Vec<TextAntidirtItem>,
+    // 请求失败时返回
+    code: i64,
+    message: String,
+}
+
+impl ApiResult for TextAntidirtRes {
+    fn code(&self) -> i64 {
+        if self.code != 0 { self.code } else { self.base.code() }
+    }
+    fn message(&self) -> String {
+        if self.message.is_empty() { self.base.message() } else { self.message.clone() }
+    }
+    fn logid(&self) -> &str {
+        self.base.logid()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+struct TextAntidirtItem {
+    msg: String,
+    code: i64,
+    task_id: String,
+    predicts: Vec<TextPredict>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+struct TextPredict {
+    prob: f64,
+    hit: bool,
+    model_name: String,
+}
+
+/**
+ * 图片检测的返回结构体，error 不设默认值：返回中没有 error 时使用 err_no，不会把其他格式的错误当作检测通过
+ */
+#[derive(Deserialize, Debug, Clone)]
+struct ImageCensorRes {
+    #[serde(flatten)]
+    base: BaseRes,
+    error: Option<i64>,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    predicts: Vec<ImagePredict>,
+}
+
+impl ApiResult for ImageCensorRes {
+    fn code(&self) -> i64 {
+        self.error.unwrap_or(self.base.err_no)
+    }
+    fn message(&self) -> String {
+        if self.message.is_empty() { self.base.message() } else { self.message.clone() }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+struct ImagePredict {
+    model_name: String,
+    hit: bool,
+}
+
+/**
+ * 命中的检测模型
+ */
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ModerationHit {
+    pub label: String, // 检测模型，例如 porn、politics、ad
+    pub confidence: Option<f64>, // 置信度，图片检测不返回
+}
+
+/**
+ * 文本检测结果
+ */
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TextCheckResult {
+    pub content: String,
+    pub hit: bool, // 是否命中任意模型，命中时不应展示
+    pub hits: Vec<ModerationHit>,
+}
+
+/**
+ * 图片检测结果
+ */
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ImageCheckResult {
+    pub hit: bool,
+    pub hits: Vec<ModerationHit>,
+}
+
+/**
+ * 待检测的图片
+ */
+#[derive(Debug, Clone)]
+pub enum ImageSource {
+    Url(String), // 图片链接
+    Data(Vec<u8>), // 图片内容
+}
+
+impl SDK {
+    /**
+     * 检测单条文本
+     */
+    pub async fn check_text(&mut self, content: &str) -> Result<TextCheckResult, DouyinError> {
+        let mut results = self.check_texts(&[content]).await?;
+        results.pop().ok_or_else(|| DouyinError::Api { code: -1, msg: "文本检测没有返回结果".into() })
+    }
+
+    /**
+     * 批量检测文本，例如推送过来的一批评论，按 TEXT_BATCH_SIZE 分批请求，返回结果与传入顺序一致
+     */
+    pub async fn check_texts<S: AsRef<str>>(&mut self, contents: &[S]) -> Result<Vec<TextCheckResult>, DouyinError> {
+        let mut results = Vec::with_capacity(contents.len());
+        for chunk in contents.chunks(TEXT_BATCH_SIZE) {
+            let tasks: Vec<_> = chunk.iter().map(|content| json!({"content": content.as_ref()})).collect();
+            let data: TextAntidirtRes = self.call_api("/api/v2/tags/text/antidirt", json!({"tasks": tasks}), None).await?;
+            results.extend(text_results(chunk, data)?);
+        }
+        Ok(results)
+    }
+
+    /**
+     * 检测图片，接口没有返回任何检测模型的结果时返回错误
+     */
+    pub async fn check_image(&mut self, image: ImageSource) -> Result<ImageCheckResult, DouyinError> {
+        let mut body = json!({"app_id": self.appid});
+        match image {
+            ImageSource::Url(url) => body["image"] = json!(url),
+            ImageSource::Data(bytes) => body["image_data"] = json!(base64::engine::general_purpose::STANDARD.encode(bytes)),
+        }
+        let data: ImageCensorRes = self.call_api("/api/apps/censor/image", body, Some("access_token")).await?;
+        image_result(data)
+    }
+}
+
+/**
+ * 把一批文本的检测结果按顺序对应到传入的文本，数量不一致或单条检测失败时返回错误
+ */
+fn text_results<S: AsRef<str>>(contents: &[S], data: TextAntidirtRes) -> Result<Vec<TextCheckResult>, DouyinError> {
+    if data.data.len() != contents.len() {
+        return Err(DouyinError::Api { code: -1, msg: format!("文本检测返回数量不一致, log_id: {}", data.logid()) });
+    }
+    let mut results = Vec::with_capacity(contents.len());
+    for (content, item) in contents.iter().zip(data.data) {
+        if item.code != 0 {
+            return Err(DouyinError::Api { code: item.code, msg: item.msg });
+        }
+        let hits: Vec<ModerationHit> = item.predicts.into_iter()
+            .filter(|predict| predict.hit)
+            .map(|predict| ModerationHit { label: predict.model_name, confidence: Some(predict.prob) })
+            .collect();
+        results.push(TextCheckResult {
+            content: content.as_ref().to_string(),
+            hit: !hits.is_empty(),
+            hits,
+        });
+    }
+    Ok(results)
+}
+
+/**
+ * 没有返回任何检测模型的结果时返回错误，不会当作检测通过
+ */
+fn image_result(data: ImageCensorRes) -> Result<ImageCheckResult, DouyinError> {
+    if data.predicts.is_empty() {
+        return Err(DouyinError::Api { code: -1, msg: "图片检测没有返回结果".into() });
+    }
+    let hits: Vec<ModerationHit> = data.predicts.into_iter()
+        .filter(|predict| predict.hit)
+        .map(|predict| ModerationHit { label: predict.model_name, confidence: None })
+        .collect();
+    Ok(ImageCheckResult { hit: !hits.is_empty(), hits })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_error_without_error_field_is_not_a_pass() {
+        let data: ImageCensorRes = serde_json::from_str(r#"{"err_no":40002,"err_tips":"access_token无效"}"#).unwrap();
+        assert_eq!(data.code(), 40002);
+        assert!(matches!(data.into_result(), Err(DouyinError::Api { code: 40002, .. })));
+
+        let data: ImageCensorRes = serde_json::from_str(r#"{"error":0,"predicts":[]}"#).unwrap();
+        assert!(matches!(image_result(data.into_result().unwrap()), Err(DouyinError::Api { code: -1, .. })));
+
+        let data: ImageCensorRes = serde_json::from_str("{}").unwrap();
+        assert!(image_result(data.into_result().unwrap()).is_err());
+    }
+
+    #[test]
+    fn image_hits() {
+        let data: ImageCensorRes = serde_json::from_str(
+            r#"{"error":0,"predicts":[{"model_name":"porn","hit":true},{"model_name":"ad","hit":false}]}"#
+        ).unwrap();
+        let result = image_result(data.into_result().unwrap()).unwrap();
+        assert!(result.hit);
+        assert_eq!(result.hits.len(), 1);
+        assert_eq!(result.hits[0].label, "porn");
+
+        let data: ImageCensorRes = serde_json::from_str(r#"{"error":1,"message":"图片下载失败"}"#).unwrap();
+        assert!(matches!(data.into_result(), Err(DouyinError::Api { code: 1, msg }) if msg == "图片下载失败"));
+    }
+
+    #[test]
+    fn text_results_follow_input_order() {
+        let data: TextAntidirtRes = serde_json::from_str(r#"{"log_id":"l1","data":[
+            {"code":0,"predicts":[{"model_name":"short_content_antispam","hit":true,"prob":0.9}]},
+            {"code":0,"predicts":[{"model_name":"short_content_antispam","hit":false,"prob":0.1}]}
+        ]}"#).unwrap();
+        let results = text_results(&["a", "b"], data).unwrap();
+        assert_eq!(results[0].content, "a");
+        assert!(results[0].hit);
+        assert_eq!(results[0].hits[0].confidence, Some(0.9));
+        assert!(!results[1].hit);
+    }
+
+    #[test]
+    fn text_results_count_mismatch_is_an_error() {
+        let data: TextAntidirtRes = serde_json::from_str(r#"{"log_id":"l1","data":[{"code":0,"predicts":[]}]}"#).unwrap();
+        assert!(matches!(text_results(&["a", "b"], data), Err(DouyinError::Api { code: -1, msg }) if msg.contains("l1")));
+
+        let data: TextAntidirtRes = serde_json::from_str(r#"{"data":[{"code":2,"msg":"内容为空"}]}"#).unwrap();
+        assert!(matches!(text_results(&[""], data), Err(DouyinError::Api { code: 2, .. })));
+
+        let data: TextAntidirtRes = serde_json::from_str(r#"{"err_no":40002,"err_tips":"access_token无效"}"#).unwrap();
+        assert_eq!(data.code(), 40002);
+    }
+}
@@ -0,0 +1,161 @@
+use serde::Serialize;
+
+use crate::{get_now_timestamp, make_random_string, secret::SecretKey, sign, DouyinError, SDK};
+
+/**
+ * 底层的签名请求，签名和发送的是同一份 body 字节，path 中的 query 原样参与签名
+ * 通过 SDK::send_signed 发送，例如：
+ * SignedRequest::new(Method::GET, "/api/xxx?a=1&b=2")
+ * SignedRequest::new(Method::POST, "/api/xxx").json(&body)?
+ */
+#[derive(Debug, Clone)]
+pub struct SignedRequest {
+    method: reqwest::Method,
+    path_and_query: String,
+    body: Vec<u8>,
+    content_type: Option<String>,
+    base_url: Option<String>,
+    key_version: String,
+    headers: Vec<(String, String)>,
+}
+
+impl SignedRequest {
+    /**
+     * path_and_query 为接口路径，可以带 query，需要调用方自行 urlencode
+     */
+    pub fn new(method: reqwest::Method, path_and_query: &str) -> Self {
+        SignedRequest {
+            method,
+            path_and_query: path_and_query.to_string(),
+            body: Vec::new(),
+            content_type: None,
+            base_url: None,
+            key_version: "1".to_string(),
+            headers: Vec::new(),
+        }
+    }
+
+    /**
+     * 原始 body，按传入的字节签名和发送
+     */
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /**
+     * 序列化为 json 作为 body，并设置 Content-Type
+     */
+    pub fn json<T: Serialize + ?Sized>(mut self, body: &T) -> Result<Self, DouyinError> {
+        self.body = serde_json::to_vec(body)?;
+        self.content_type = Some("application/json".to_string());
+        Ok(self)
+    }
+
+    pub fn content_type(mut self, content_type: &str) -> Self {
+        self.content_type = Some(content_type.to_string());
+        self
+    }
+
+    /**
+     * 请求的域名，默认为 SDK 的 base_url
+     */
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        self.base_url = Some(base_url.trim_end_matches('/').to_string());
+        self
+    }
+
+    /**
+     * 开发者后台配置的公钥版本，默认为1
+     */
+    pub fn key_version(mut self, key_version: &str) -> Self {
+        self.key_version = key_version.to_string();
+        self
+    }
+
+    /**
+     * 额外的请求头
+     */
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /**
+     * 签名的原文
+     */
+    pub fn string_to_sign(&self, timestamp: &str, nonce_str: &str) -> Vec<u8> {
+        sign::string_to_sign(self.method.as_str(), &self.path_and_query, timestamp, nonce_str, &self.body)
+    }
+
+    fn path(&self) -> &str {
+        self.path_and_query.split('?').next().unwrap_or_default()
+    }
+}
+
+impl SDK {
+    /**
+     * 签名并发送请求，请求头包含 Byte-Authorization 和 access-token
+     */
+    pub async fn send_signed(&mut self, request: SignedRequest) -> Result<reqwest::Response, DouyinError> {
+        let timestamp = get_now_timestamp(false).to_string();
+        let nonce_str = make_random_string();
+        let content = request.string_to_sign(&timestamp, &nonce_str);
+        let private_key = match &self.private_key {
+            Some(private_key) => private_key,
+            // SDK::new 构造时没有提前解析私钥，第一次签名时解析后保存
+            None => {
+                if self.app_private_key.expose().trim().is_empty() {
+                    return Err(DouyinError::Config("未设置app_private_key，无法签名".into()));
+                }
+                let (private_key, _) = sign::parse_pri_key(self.app_private_key.expose(), Some(self.pkcs_type))
+                    .map_err(DouyinError::PrivateKey)?;
+                self.private_key.insert(SecretKey(private_key))
+            },
+        };
+        let signature = sign::sign_base64_with_key(&content, &private_key.0).map_err(DouyinError::PrivateKey)?;
+        let byte_authorization = sign::byte_authorization(&self.appid, &nonce_str, &timestamp, &request.key_version, &signature);
+        let access_token = self.get_access_token().await?;
+        let base_url = request.base_url.as_deref().unwrap_or(&self.base_url);
+        let mut builder = self.client.request(request.method.clone(), format!("{}{}", base_url, request.path_and_query))
+            .header("Byte-Authorization", byte_authorization)
+            .header("Accept", "application/json")
+            .header("access-token", access_token);
+        if let Some(content_type) = &request.content_type {
+            builder = builder.header(reqwest::header::CONTENT_TYPE, content_type);
+        }
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        if !request.body.is_empty() {
+            builder = builder.body(request.body.clone());
+        }
+        self.send(request.path(), builder).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DouyinConfig;
+
+    #[test]
+    fn string_to_sign_uses_exact_body_bytes() {
+        let request = SignedRequest::new(reqwest::Method::POST, "/path?b=2&a=1").body(r#"{"b":2, "a":1}"#);
+        assert_eq!(request.string_to_sign("1700000000", "nonce"), b"POST\n/path?b=2&a=1\n1700000000\nnonce\n{\"b\":2, \"a\":1}\n");
+        assert_eq!(request.path(), "/path");
+        let request = SignedRequest::new(reqwest::Method::GET, "/path");
+        assert_eq!(request.string_to_sign("1700000000", "nonce"), b"GET\n/path\n1700000000\nnonce\n\n");
+    }
+
+    #[tokio::test]
+    async fn send_signed_without_valid_key_returns_error() {
+        let mut sdk = SDK::new(DouyinConfig { appid: "appid", secret: "secret", ..Default::default() });
+        let res = sdk.send_signed(SignedRequest::new(reqwest::Method::GET, "/path")).await;
+        assert!(matches!(res, Err(DouyinError::Config(_))));
+
+        let mut sdk = SDK::new(DouyinConfig { appid: "appid", secret: "secret", app_private_key: "not a key", ..Default::default() });
+        let res = sdk.send_signed(SignedRequest::new(reqwest::Method::GET, "/path")).await;
+        assert!(matches!(res, Err(DouyinError::PrivateKey(_))));
+    }
+}
@@ -0,0 +1,418 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{get_now_timestamp, make_random_string, telemetry, DouyinError, Secret, SDK};
+
+/**
+ * 缓存格式的版本号，格式变化后旧的缓存会被忽略
+ */
+pub const TOKEN_CACHE_VERSION: u32 = 1;
+
+/**
+ * 允许缓存的签发时间比当前时间晚多少秒，超过视为时钟回拨，缓存不可用
+ */
+const CLOCK_SKEW_TOLERANCE: u64 = 60;
+
+/**
+ * 缓存的 access_token
+ * 保存签发时间和接口返回的原始有效期，而不是过期的时间戳，安全余量在读取时按当前配置计算
+ */
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct CachedToken {
+    pub version: u32,
+    pub appid: String, // 缓存对应的 appid，读取时会校验，不会把A应用的token用到B应用上
+    pub access_token: Secret,
+    pub issued_at: u64, // 获取token时的时间戳（秒）
+    pub expires_in: u64, // 接口返回的有效期（秒）
+}
+
+impl CachedToken {
+    pub fn new(appid: &str, access_token: Secret, expires_in: u64) -> Self {
+        CachedToken {
+            version: TOKEN_CACHE_VERSION,
+            appid: appid.to_string(),
+            access_token,
+            issued_at: get_now_timestamp(false),
+            expires_in,
+        }
+    }
+
+    /**
+     * 过期的时间戳（秒）
+     */
+    pub fn expires_at(&self) -> u64 {
+        // 缓存文件损坏时字段可能很大，不能溢出
+        self.issued_at.saturating_add(self.expires_in)
+    }
+
+    /**
+     * 扣除安全余量后还能使用多久，不可用时返回 None
+     * 安全余量不小于有效期时按有效期的一半计算；签发时间在未来（时钟回拨）时视为不可用
+     */
+    pub fn remaining(&self, appid: &str, now: u64, safety_margin: u64) -> Option<std::time::Duration> {
+        if self.version != TOKEN_CACHE_VERSION || self.appid != appid {
+            return None;
+        }
+        if self.issued_at > now.saturating_add(CLOCK_SKEW_TOLERANCE) {
+            return None;
+        }
+        let usable = if safety_margin < self.expires_in {
+            self.expires_in - safety_margin
+        } else {
+            self.expires_in / 2
+        };
+        let deadline = self.issued_at.saturating_add(usable);
+        if deadline <= now {
+            return None;
+        }
+        Some(std::time::Duration::from_secs(deadline - now))
+    }
+}
+
+/**
+ * access_token 的缓存，SDK 在内存中的token过期后会优先从这里读取，
+ * 读取不到或已过期才会重新请求抖音接口。
+ * 以 appid 为键，同一个 store 可以被多个 SDK 共享。
+ */
+#[async_trait]
+pub trait TokenStore: std::fmt::Debug + Send + Sync {
+    /**
+     * 读取缓存，不存在或读取失败时返回 None
+     */
+    async fn load(&self, appid: &str) -> Option<CachedToken>;
+
+    /**
+     * 写入缓存
+     */
+    async fn save(&self, appid: &str, data: &CachedToken) -> Result<(), DouyinError>;
+
+    /**
+     * 删除缓存，token被吊销时调用，避免重启后又读到失效的token
+     */
+    async fn remove(&self, appid: &str) -> Result<(), DouyinError>;
+
+    /**
+     * 只有缓存中的token等于 access_token 时才删除
+     * 多个实例共享缓存时，避免把其他实例刚刷新的token当作失效的token删除
+     * 默认实现先读取再删除，不是原子操作，自定义的 store 可以按需覆盖
+     */
+    async fn remove_if_matches(&self, appid: &str, access_token: &Secret) -> Result<(), DouyinError> {
+        match self.load(appid).await {
+            Some(data) if data.access_token == *access_token => self.remove(appid).await,
+            _ => Ok(()),
+        }
+    }
+}
+
+/**
+ * 文件缓存，当进程重启后优先读取文件缓存（SDK::new 默认使用）
+ * 路径中的 `{appid}` 会被替换为实际的 appid，多个应用共享同一个 store 时文件不会互相覆盖
+ */
+#[derive(Debug, Clone)]
+pub struct FileTokenStore {
+    pub path: String,
+}
+
+impl FileTokenStore {
+    pub fn new(path: &str) -> Self {
+        FileTokenStore { path: path.to_string() }
+    }
+
+    /**
+     * 按 appid 区分的缓存文件，文件名为 douyin_access_token_{appid}.json
+     */
+    pub fn per_app() -> Self {
+        Self::new("douyin_access_token_{appid}.json")
+    }
+
+    /**
+     * 缓存文件的路径，绝对路径直接使用，相对路径相对于exe所在目录
+     */
+    fn file_path(&self, appid: &str) -> std::io::Result<PathBuf> {
+        let name = self.path.replace("{appid}", appid);
+        let path = PathBuf::from(&name);
+        if path.is_absolute() {
+            Ok(path)
+        } else {
+            SDK::get_exe_path(&name)
+        }
+    }
+}
+
+/**
+ * 多个进程共用缓存文件时，通过 `{文件名}.lock` 加锁，读共享、写独占
+ */
+fn open_lock_file(path: &Path) -> std::io::Result<File> {
+    let mut lock_path = path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    let mut options = OpenOptions::new();
+    options.create(true).truncate(false).write(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(lock_path)
+}
+
+fn read_cache_file(path: &Path) -> std::io::Result<String> {
+    // 先打开缓存文件，文件不存在时不会创建锁文件
+    let mut file = File::open(path)?;
+    let lock = fd_lock::RwLock::new(open_lock_file(path)?);
+    let _guard = lock.read()?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    Ok(contents)
+}
+
+/**
+ * 先写入同目录下的临时文件并fsync，再rename覆盖，进程中途崩溃也不会留下写了一半的缓存
+ * unix 下文件权限为 0600
+ */
+fn write_cache_file(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut lock = fd_lock::RwLock::new(open_lock_file(path)?);
+    let _guard = lock.write()?;
+
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(format!(".{}.tmp", make_random_string()));
+    let tmp_path = PathBuf::from(tmp_path);
+    let mut options = OpenOptions::new();
+    options.create_new(true).write(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let res = options.open(&tmp_path).and_then(|mut file| {
+        file.write_all(contents)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, path)
+    });
+    if res.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    res?;
+    // rename 后同步目录，保证断电后文件名也已落盘
+    #[cfg(unix)]
+    if let Some(dir) = path.parent() {
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/**
+ * access_token 不为 None 时，只有文件中的token等于它才删除，读取和删除在同一个写锁内
+ */
+fn remove_cache_file(path: &Path, access_token: Option<&Secret>) -> std::io::Result<()> {
+    let mut lock = fd_lock::RwLock::new(open_lock_file(path)?);
+    let _guard = lock.write()?;
+    if let Some(access_token) = access_token {
+        let contents = match std::fs::read_to_string(path) {
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            contents => contents?,
+        };
+        let matches = serde_json::from_str::<CachedToken>(&contents)
+            .is_ok_and(|data| data.access_token == *access_token);
+        if !matches {
+            return Ok(());
+        }
+    }
+    match std::fs::remove_file(path) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+#[async_trait]
+impl TokenStore for FileTokenStore {
+    /**
+     * 读取access_token文件
+     */
+    async fn load(&self, appid: &str) -> Option<CachedToken> {
+        let path = self.file_path(appid).ok()?;
+        let read_path = path.clone();
+        let contents = tokio::task::spawn_blocking(move || read_cache_file(&read_path)).await
+            .unwrap_or_else(|err| Err(std::io::Error::other(err)));
+        match contents {
+            Ok(json_text) => match serde_json::from_str::<CachedToken>(&json_text) {
+                // 旧版本或其他应用的缓存直接忽略
+                Ok(data) if data.version == TOKEN_CACHE_VERSION && data.appid == appid => Some(data),
+                Ok(_) => None,
+                Err(err) => {
+                    telemetry::cache_file_invalid(&path, &err);
+                    None
+                }
+            },
+            Err(err) => {
+                // 文件不存在时程序继续执行，稍后转到请求数据
+                telemetry::cache_file_missing(&path, &err);
+                None
+            }
+        }
+    }
+
+    /**
+     * 写入access_token到缓存文件
+     */
+    async fn save(&self, appid: &str, data: &CachedToken) -> Result<(), DouyinError> {
+        let path = self.file_path(appid)?;
+        let json_str = serde_json::to_string(data)?;
+        let write_path = path.clone();
+        tokio::task::spawn_blocking(move || write_cache_file(&write_path, json_str.as_bytes())).await
+            .unwrap_or_else(|err| Err(std::io::Error::other(err)))?;
+        telemetry::cache_file_written(&path);
+        Ok(())
+    }
+
+    async fn remove(&self, appid: &str) -> Result<(), DouyinError> {
+        let path = self.file_path(appid)?;
+        tokio::task::spawn_blocking(move || remove_cache_file(&path, None)).await
+            .unwrap_or_else(|err| Err(std::io::Error::other(err)))?;
+        Ok(())
+    }
+
+    async fn remove_if_matches(&self, appid: &str, access_token: &Secret) -> Result<(), DouyinError> {
+        let path = self.file_path(appid)?;
+        let access_token = access_token.clone();
+        tokio::task::spawn_blocking(move || remove_cache_file(&path, Some(&access_token))).await
+            .unwrap_or_else(|err| Err(std::io::Error::other(err)))?;
+        Ok(())
+    }
+}
+
+/**
+ * 内存缓存，进程重启后失效，适合不方便写文件的环境
+ */
+#[derive(Debug, Default)]
+pub struct MemoryTokenStore {
+    tokens: Mutex<HashMap<String, CachedToken>>,
+}
+
+impl MemoryTokenStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl TokenStore for MemoryTokenStore {
+    async fn load(&self, appid: &str) -> Option<CachedToken> {
+        self.tokens.lock().unwrap().get(appid).cloned()
+    }
+
+    async fn save(&self, appid: &str, data: &CachedToken) -> Result<(), DouyinError> {
+        self.tokens.lock().unwrap().insert(appid.to_string(), data.clone());
+        Ok(())
+    }
+
+    async fn remove(&self, appid: &str) -> Result<(), DouyinError> {
+        self.tokens.lock().unwrap().remove(appid);
+        Ok(())
+    }
+
+    async fn remove_if_matches(&self, appid: &str, access_token: &Secret) -> Result<(), DouyinError> {
+        let mut tokens = self.tokens.lock().unwrap();
+        if tokens.get(appid).is_some_and(|data| data.access_token == *access_token) {
+            tokens.remove(appid);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn token(issued_at: u64, expires_in: u64) -> CachedToken {
+        CachedToken {
+            version: TOKEN_CACHE_VERSION,
+            appid: "appid".to_string(),
+            access_token: "token".into(),
+            issued_at,
+            expires_in,
+        }
+    }
+
+    #[test]
+    fn remaining_subtracts_safety_margin() {
+        let data = token(1000, 7200);
+        assert_eq!(data.expires_at(), 8200);
+        assert_eq!(data.remaining("appid", 1000, 600), Some(Duration::from_secs(6600)));
+        assert_eq!(data.remaining("appid", 7000, 600), Some(Duration::from_secs(600)));
+        assert_eq!(data.remaining("appid", 7600, 600), None);
+        assert_eq!(data.remaining("appid", 8000, 600), None);
+    }
+
+    #[test]
+    fn safety_margin_is_clamped_to_half_of_expires_in() {
+        let data = token(1000, 600);
+        assert_eq!(data.remaining("appid", 1000, 600), Some(Duration::from_secs(300)));
+        assert_eq!(data.remaining("appid", 1000, 3600), Some(Duration::from_secs(300)));
+        assert_eq!(data.remaining("appid", 1300, 3600), None);
+        assert_eq!(data.remaining("appid", 1000, 599), Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn remaining_rejects_other_app_old_version_and_future_issued_at() {
+        let data = token(1000, 7200);
+        assert_eq!(data.remaining("other", 1000, 600), None);
+        assert_eq!(CachedToken { version: TOKEN_CACHE_VERSION - 1, ..data.clone() }.remaining("appid", 1000, 600), None);
+        assert!(data.remaining("appid", 1000 - CLOCK_SKEW_TOLERANCE, 600).is_some());
+        assert_eq!(data.remaining("appid", 1000 - CLOCK_SKEW_TOLERANCE - 1, 600), None);
+    }
+
+    #[test]
+    fn corrupted_fields_do_not_overflow() {
+        let data = token(u64::MAX - 10, u64::MAX);
+        assert_eq!(data.expires_at(), u64::MAX);
+        assert_eq!(data.remaining("appid", 1000, 600), None);
+        assert_eq!(data.remaining("appid", u64::MAX - 20, 600), Some(Duration::from_secs(20)));
+        assert_eq!(token(1000, u64::MAX).remaining("appid", 1000, 600), Some(Duration::from_secs(u64::MAX - 1000)));
+    }
+
+    #[tokio::test]
+    async fn file_store_ignores_old_cache_format() {
+        let dir = std::env::temp_dir().join(format!("douyin_sdk_test_{}", make_random_string()));
+        let path = dir.join("token_{appid}.json");
+        let store = FileTokenStore::new(path.to_str().unwrap());
+        let file_path = dir.join("token_appid.json");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // 0.1 版本的缓存格式，只有 access_token 和过期时间戳
+        std::fs::write(&file_path, r#"{"access_token":"old","expires_in":99999999999}"#).unwrap();
+        assert!(store.load("appid").await.is_none());
+
+        let data = CachedToken::new("appid", "new".into(), 7200);
+        store.save("appid", &data).await.unwrap();
+        assert_eq!(store.load("appid").await.unwrap().access_token, data.access_token);
+        assert!(store.load("other").await.is_none());
+
+        let mut old = serde_json::to_value(&data).unwrap();
+        old["version"] = (TOKEN_CACHE_VERSION + 1).into();
+        std::fs::write(&file_path, old.to_string()).unwrap();
+        assert!(store.load("appid").await.is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn remove_if_matches_keeps_token_refreshed_by_other_instance() {
+        let dir = std::env::temp_dir().join(format!("douyin_sdk_test_{}", make_random_string()));
+        let file_store = FileTokenStore::new(dir.join("token_{appid}.json").to_str().unwrap());
+        let stores: [&dyn TokenStore; 2] = [&MemoryTokenStore::new(), &file_store];
+        for store in stores {
+            let fresh = CachedToken::new("appid", "fresh".into(), 7200);
+            store.save("appid", &fresh).await.unwrap();
+            store.remove_if_matches("appid", &"stale".into()).await.unwrap();
+            assert_eq!(store.load("appid").await.unwrap().access_token, fresh.access_token);
+            store.remove_if_matches("appid", &"fresh".into()).await.unwrap();
+            assert!(store.load("appid").await.is_none());
+            store.remove_if_matches("appid", &"fresh".into()).await.unwrap();
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
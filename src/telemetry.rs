@@ -0,0 +1,290 @@
+/*
+* 请求和 access_token 缓存的埋点
+* 开启 `tracing` feature 后输出 tracing 的 span 和事件，未开启时为空实现，不会向标准输出打印任何内容
+* 开启 `metrics` feature 后通过 metrics 门面输出计数器和直方图，需要使用方自行安装 recorder/exporter
+*/
+use std::fmt::Display;
+use std::future::Future;
+use std::path::Path;
+
+#[cfg(feature = "tracing")]
+use tracing::{field::Empty, Instrument};
+
+use crate::{api::ApiResult, DouyinError};
+
+/**
+ * 一次平台接口调用的 span
+ */
+pub(crate) struct CallSpan {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    #[cfg(any(feature = "tracing", feature = "metrics"))]
+    start: std::time::Instant,
+    #[cfg(feature = "metrics")]
+    kind: &'static str,
+    #[cfg(feature = "metrics")]
+    path: String,
+}
+
+impl CallSpan {
+    /**
+     * 单个 http 请求，重试时记录 attempt
+     */
+    pub(crate) fn request(path: &str) -> Self {
+        #[cfg(not(any(feature = "tracing", feature = "metrics")))]
+        let _ = path;
+        CallSpan {
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!(
+                "douyin_request",
+                path,
+                attempt = Empty,
+                status = Empty,
+                latency_ms = Empty,
+                outcome = Empty
+            ),
+            #[cfg(any(feature = "tracing", feature = "metrics"))]
+            start: std::time::Instant::now(),
+            #[cfg(feature = "metrics")]
+            kind: "request",
+            #[cfg(feature = "metrics")]
+            path: path.to_string(),
+        }
+    }
+
+    /**
+     * 直播数据开放任务，记录直播间和消息类型
+     */
+    pub(crate) fn task(path: &str, roomid: &str, msg_type: &str) -> Self {
+        #[cfg(not(any(feature = "tracing", feature = "metrics")))]
+        let _ = path;
+        #[cfg(not(feature = "tracing"))]
+        let _ = (roomid, msg_type);
+        CallSpan {
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!(
+                "douyin_task",
+                path,
+                roomid,
+                msg_type,
+                logid = Empty,
+                err_no = Empty,
+                latency_ms = Empty,
+                outcome = Empty
+            ),
+            #[cfg(any(feature = "tracing", feature = "metrics"))]
+            start: std::time::Instant::now(),
+            #[cfg(feature = "metrics")]
+            kind: "task",
+            #[cfg(feature = "metrics")]
+            path: path.to_string(),
+        }
+    }
+
+    /**
+     * 其他接口调用
+     */
+    pub(crate) fn call(path: &str) -> Self {
+        #[cfg(not(any(feature = "tracing", feature = "metrics")))]
+        let _ = path;
+        CallSpan {
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!(
+                "douyin_call",
+                path,
+                logid = Empty,
+                err_no = Empty,
+                latency_ms = Empty,
+                outcome = Empty
+            ),
+            #[cfg(any(feature = "tracing", feature = "metrics"))]
+            start: std::time::Instant::now(),
+            #[cfg(feature = "metrics")]
+            kind: "call",
+            #[cfg(feature = "metrics")]
+            path: path.to_string(),
+        }
+    }
+
+    pub(crate) fn attempt(&self, attempt: u32) {
+        #[cfg(not(feature = "tracing"))]
+        let _ = attempt;
+        #[cfg(feature = "tracing")]
+        self.span.record("attempt", attempt);
+    }
+
+    pub(crate) fn status(&self, status: u16) {
+        #[cfg(not(feature = "tracing"))]
+        let _ = status;
+        #[cfg(feature = "tracing")]
+        self.span.record("status", status);
+    }
+
+    /**
+     * 记录接口返回的 logid 和错误码
+     */
+    pub(crate) fn response(&self, logid: &str, err_no: i64) {
+        #[cfg(not(any(feature = "tracing", feature = "metrics")))]
+        let _ = err_no;
+        #[cfg(not(feature = "tracing"))]
+        let _ = logid;
+        #[cfg(feature = "tracing")]
+        {
+            self.span.record("logid", logid);
+            self.span.record("err_no", err_no);
+        }
+        #[cfg(feature = "metrics")]
+        api_error(&self.path, err_no);
+    }
+
+    /**
+     * 记录耗时和结果
+     */
+    pub(crate) fn finish<T, E: Display>(&self, res: &Result<T, E>) {
+        #[cfg(not(any(feature = "tracing", feature = "metrics")))]
+        let _ = res;
+        #[cfg(feature = "tracing")]
+        {
+            self.span
+                .record("latency_ms", self.start.elapsed().as_millis() as u64);
+            match res {
+                Ok(_) => {
+                    self.span.record("outcome", "ok");
+                }
+                Err(err) => {
+                    self.span.record("outcome", "error");
+                    self.span
+                        .in_scope(|| tracing::warn!(error = %err, "抖音接口调用失败"));
+                }
+            }
+        }
+        #[cfg(feature = "metrics")]
+        {
+            let outcome = if res.is_ok() { "ok" } else { "error" };
+            metrics::counter!("douyin_calls_total", "kind" => self.kind, "path" => self.path.clone(), "outcome" => outcome).increment(1);
+            metrics::histogram!("douyin_call_duration_seconds", "kind" => self.kind, "path" => self.path.clone()).record(self.start.elapsed().as_secs_f64());
+        }
+    }
+
+    /**
+     * 在 span 内执行接口调用，记录返回的 logid、错误码和结果
+     */
+    pub(crate) async fn trace<T: ApiResult, F>(&self, fut: F) -> Result<T, DouyinError>
+    where
+        F: Future<Output = Result<T, DouyinError>>,
+    {
+        let res = self.instrument(fut).await;
+        if let Ok(data) = &res {
+            self.response(data.logid(), data.code());
+        }
+        self.finish(&res);
+        res
+    }
+
+    /**
+     * 在 span 内执行
+     */
+    pub(crate) async fn instrument<F: Future>(&self, fut: F) -> F::Output {
+        #[cfg(feature = "tracing")]
+        return fut.instrument(self.span.clone()).await;
+        #[cfg(not(feature = "tracing"))]
+        return fut.await;
+    }
+}
+
+/**
+ * 接口返回非0错误码时计数，按接口和错误码区分
+ */
+pub(crate) fn api_error(path: &str, err_no: i64) {
+    #[cfg(not(feature = "metrics"))]
+    let _ = (path, err_no);
+    #[cfg(feature = "metrics")]
+    if err_no != 0 {
+        metrics::counter!("douyin_api_errors_total", "path" => path.to_string(), "err_no" => err_no.to_string()).increment(1);
+    }
+}
+
+pub(crate) fn token_cache_hit(appid: &str) {
+    #[cfg(not(feature = "tracing"))]
+    let _ = appid;
+    #[cfg(feature = "tracing")]
+    tracing::debug!(appid, "使用缓存的access_token");
+    #[cfg(feature = "metrics")]
+    metrics::counter!("douyin_token_cache_total", "result" => "hit").increment(1);
+}
+
+pub(crate) fn token_cache_miss(appid: &str) {
+    #[cfg(not(feature = "tracing"))]
+    let _ = appid;
+    #[cfg(feature = "tracing")]
+    tracing::debug!(appid, "access_token缓存不存在或已过期");
+    #[cfg(feature = "metrics")]
+    metrics::counter!("douyin_token_cache_total", "result" => "miss").increment(1);
+}
+
+pub(crate) fn token_refreshed(appid: &str, expires_in: u64) {
+    #[cfg(not(feature = "tracing"))]
+    let _ = (appid, expires_in);
+    #[cfg(feature = "tracing")]
+    tracing::info!(appid, expires_in, "access_token刷新成功");
+    #[cfg(feature = "metrics")]
+    metrics::counter!("douyin_token_refresh_total", "outcome" => "ok").increment(1);
+}
+
+pub(crate) fn token_refresh_failed(appid: &str, err: &dyn Display) {
+    #[cfg(not(feature = "tracing"))]
+    let _ = (appid, err);
+    #[cfg(feature = "tracing")]
+    tracing::warn!(appid, error = %err, "access_token刷新失败");
+    #[cfg(feature = "metrics")]
+    metrics::counter!("douyin_token_refresh_total", "outcome" => "error").increment(1);
+}
+
+/**
+ * 系统时间发生跳变，drift 为系统时间相对单调时钟多走的秒数
+ */
+pub(crate) fn clock_jump(appid: &str, drift: i64) {
+    #[cfg(not(feature = "tracing"))]
+    let _ = (appid, drift);
+    #[cfg(feature = "tracing")]
+    tracing::warn!(appid, drift, "检测到系统时间跳变");
+    #[cfg(feature = "metrics")]
+    metrics::counter!("douyin_clock_jumps_total").increment(1);
+}
+
+pub(crate) fn cache_file_missing(path: &Path, err: &dyn Display) {
+    #[cfg(not(feature = "tracing"))]
+    let _ = (path, err);
+    #[cfg(feature = "tracing")]
+    tracing::debug!(path = %path.display(), error = %err, "token缓存文件不存在");
+}
+
+pub(crate) fn cache_file_invalid(path: &Path, err: &dyn Display) {
+    #[cfg(not(feature = "tracing"))]
+    let _ = (path, err);
+    #[cfg(feature = "tracing")]
+    tracing::warn!(path = %path.display(), error = %err, "token缓存文件解析失败");
+}
+
+pub(crate) fn cache_file_written(path: &Path) {
+    #[cfg(not(feature = "tracing"))]
+    let _ = path;
+    #[cfg(feature = "tracing")]
+    tracing::debug!(path = %path.display(), "token缓存写入文件成功");
+}
+
+pub(crate) fn token_invalidated(appid: &str) {
+    #[cfg(not(feature = "tracing"))]
+    let _ = appid;
+    #[cfg(feature = "tracing")]
+    tracing::warn!(appid, "access_token已失效，清除缓存后重新获取");
+    #[cfg(feature = "metrics")]
+    metrics::counter!("douyin_token_invalidated_total").increment(1);
+}
+
+pub(crate) fn token_save_failed(appid: &str, err: &dyn Display) {
+    #[cfg(not(feature = "tracing"))]
+    let _ = (appid, err);
+    #[cfg(feature = "tracing")]
+    tracing::warn!(appid, error = %err, "access_token写入缓存失败");
+}
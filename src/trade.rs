@@ -0,0 +1,341 @@
+/*
+* 交易系统 v2
+* 请求 open_base_url（open.douyin.com），使用与直播数据开放相同的 Byte-Authorization 签名，需要设置 app_private_key。
+*/
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{api::{self, ApiRes, ApiResult, Endpoint}, pay::{Fen, RefundStatus}, telemetry, DouyinError, SDK};
+
+/**
+ * 交易系统接口的返回结构体，err_no/err_msg/log_id 在 ApiRes 中
+ */
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+struct TradeRes<T> {
+    data: T,
+}
+
+/**
+ * 商品信息
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct TradeSku {
+    pub sku_id: String, // 开发者侧的商品 id
+    pub price: Fen, // 单价
+    pub quantity: u32, // 数量
+    pub title: String, // 商品标题
+    pub image_list: Vec<String>, // 商品图片
+    #[serde(rename = "type")]
+    pub sku_type: i64, // 商品类型，在开发者后台->交易系统->商品类型中查看
+    pub tag_group_id: String, // 交易规则标签组
+}
+
+/**
+ * 订单详情页
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct OrderEntrySchema {
+    pub path: String, // 小程序页面路径
+    pub params: String, // 页面参数，json 字符串
+}
+
+/**
+ * 预下单参数，可选字段直接赋值
+ */
+#[derive(Serialize, Debug, Clone)]
+pub struct CreateTradeOrder {
+    pub out_order_no: String, // 开发者侧的订单号
+    pub sku_list: Vec<TradeSku>,
+    pub total_amount: Fen, // 订单总金额
+    pub order_entry_schema: OrderEntrySchema,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pay_expire_seconds: Option<u64>, // 支付超时时间（秒），默认300
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pay_notify_url: Option<String>, // 支付结果回调地址，不传时使用后台配置的地址
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub merchant_uid: Option<String>, // 收款商户号
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cp_extra: Option<String>, // 开发者自定义字段，回调原样回传
+}
+
+impl CreateTradeOrder {
+    /**
+     * total_amount 按 sku 的单价和数量计算，金额溢出时返回 DouyinError::InvalidParam
+     */
+    pub fn new(out_order_no: &str, sku_list: Vec<TradeSku>, order_entry_schema: OrderEntrySchema) -> Result<Self, DouyinError> {
+        let total_amount = sku_list.iter()
+            .map(|sku| sku.price.checked_mul(sku.quantity.into()))
+            .try_fold(Fen(0), |total, amount| total.checked_add(amount?))
+            .ok_or_else(|| DouyinError::InvalidParam("订单总金额超出范围".into()))?;
+        Ok(CreateTradeOrder {
+            out_order_no: out_order_no.to_string(),
+            total_amount,
+            sku_list,
+            order_entry_schema,
+            pay_expire_seconds: None,
+            pay_notify_url: None,
+            merchant_uid: None,
+            cp_extra: None,
+        })
+    }
+}
+
+/**
+ * 预下单结果
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct TradeOrderCreated {
+    pub order_id: String, // 抖音侧的订单号
+    pub pay_order_id: String, // 支付单号
+}
+
+/**
+ * 交易系统的订单支付状态
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TradePayStatus {
+    /// 支付中
+    #[default]
+    Process,
+    /// 支付成功
+    Success,
+    /// 支付失败
+    Fail,
+    /// 支付超时
+    Timeout,
+    /// 新增的未知状态
+    #[serde(other)]
+    Unknown,
+}
+
+/**
+ * 子订单，退款和核销都以子订单为单位
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ItemOrder {
+    pub item_order_id: String, // 抖音侧的子订单号
+    pub sku_id: String, // 开发者侧的商品 id
+    pub item_order_amount: Fen, // 子订单金额
+}
+
+/**
+ * 查询订单的结果
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct TradeOrderInfo {
+    pub order_id: String, // 抖音侧的订单号
+    pub out_order_no: String, // 开发者侧的订单号
+    pub pay_status: TradePayStatus,
+    pub total_amount: Fen, // 订单总金额
+    pub discount_amount: Fen, // 优惠金额
+    pub pay_channel: i64, // 支付渠道，1 微信 2 支付宝 10 抖音支付
+    pub channel_pay_id: String, // 支付渠道侧的单号
+    pub merchant_uid: String, // 收款商户号
+    pub pay_time: String, // 支付时间，例如 2023-01-01 00:00:00
+    pub item_order_list: Vec<ItemOrder>,
+    pub cp_extra: String, // 下单时传入的自定义字段
+}
+
+/**
+ * 子订单的退款金额
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ItemOrderRefund {
+    pub item_order_id: String, // 抖音侧的子订单号
+    pub refund_amount: Fen, // 退款金额
+}
+
+/**
+ * 退款参数，可选字段直接赋值
+ */
+#[derive(Serialize, Debug, Clone)]
+pub struct CreateTradeRefund {
+    pub out_order_no: String, // 开发者侧的订单号
+    pub out_refund_no: String, // 开发者侧的退款单号
+    pub order_entry_schema: OrderEntrySchema, // 退款详情页
+    pub item_order_detail: Vec<ItemOrderRefund>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notify_url: Option<String>, // 退款结果回调地址，不传时使用后台配置的地址
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cp_extra: Option<String>, // 开发者自定义字段，回调原样回传
+}
+
+impl CreateTradeRefund {
+    pub fn new(out_order_no: &str, out_refund_no: &str, item_order_detail: Vec<ItemOrderRefund>, order_entry_schema: OrderEntrySchema) -> Self {
+        CreateTradeRefund {
+            out_order_no: out_order_no.to_string(),
+            out_refund_no: out_refund_no.to_string(),
+            order_entry_schema,
+            item_order_detail,
+            notify_url: None,
+            cp_extra: None,
+        }
+    }
+}
+
+/**
+ * 发起退款的结果
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct TradeRefundCreated {
+    pub refund_id: String, // 抖音侧的退款单号
+    pub refund_audit_deadline: u64, // 退款审核的截止时间戳（秒），需要开发者审核时返回
+}
+
+/**
+ * 查询退款的结果
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct TradeRefundInfo {
+    pub refund_id: String, // 抖音侧的退款单号
+    pub out_refund_no: String, // 开发者侧的退款单号
+    pub order_id: String, // 抖音侧的订单号
+    pub refund_status: RefundStatus,
+    pub refund_total_amount: Fen, // 退款总金额
+    pub refund_at: u64, // 退款时间戳（毫秒）
+    pub item_order_detail: Vec<ItemOrderRefund>,
+    pub cp_extra: String, // 退款时传入的自定义字段
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+struct TradeRefundList {
+    refund_list: Vec<TradeRefundInfo>,
+}
+
+impl SDK {
+    /**
+     * 交易系统->预下单
+     */
+    pub async fn create_trade_order(&mut self, order: &CreateTradeOrder) -> Result<TradeOrderCreated, DouyinError> {
+        let body = serde_json::to_value(order)?;
+        self.trade_request("/api/apps/trade/v2/order/create_order", body).await
+    }
+
+    /**
+     * 交易系统->查询订单，order_id 和 out_order_no 传一个即可
+     */
+    pub async fn query_trade_order(&mut self, order_id: Option<&str>, out_order_no: Option<&str>) -> Result<TradeOrderInfo, DouyinError> {
+        if order_id.is_none() && out_order_no.is_none() {
+            return Err(DouyinError::InvalidParam("order_id和out_order_no不能都为空".into()));
+        }
+        // 只传调用方给出的单号，另一个字段不传，不能传空字符串
+        let mut body = json!({});
+        if let Some(order_id) = order_id {
+            body["order_id"] = json!(order_id);
+        }
+        if let Some(out_order_no) = out_order_no {
+            body["out_order_no"] = json!(out_order_no);
+        }
+        self.trade_request("/api/apps/trade/v2/order/query_order", body).await
+    }
+
+    /**
+     * 交易系统->发起退款
+     */
+    pub async fn create_trade_refund(&mut self, refund: &CreateTradeRefund) -> Result<TradeRefundCreated, DouyinError> {
+        let body = serde_json::to_value(refund)?;
+        self.trade_request("/api/apps/trade/v2/refund/create_refund", body).await
+    }
+
+    /**
+     * 交易系统->查询退款，返回 out_refund_no 对应的退款单
+     */
+    pub async fn query_trade_refund(&mut self, out_refund_no: &str) -> Result<Vec<TradeRefundInfo>, DouyinError> {
+        let body = json!({ "out_refund_no": out_refund_no });
+        let data: TradeRefundList = self.trade_request("/api/apps/trade/v2/refund/query_refund", body).await?;
+        Ok(data.refund_list)
+    }
+
+    /**
+     * 交易系统->核销，item_order_ids 为空时核销整个订单
+     */
+    pub async fn push_trade_delivery(&mut self, out_order_no: &str, item_order_ids: &[&str]) -> Result<(), DouyinError> {
+        let item_order_list: Vec<Value> = item_order_ids.iter()
+            .map(|item_order_id| json!({ "item_order_id": item_order_id }))
+            .collect();
+        let body = json!({
+            "out_order_no": out_order_no,
+            "use_all": item_order_list.is_empty(),
+            "item_order_list": item_order_list,
+        });
+        let _: Value = self.trade_request("/api/apps/trade/v2/fulfillment/push_delivery", body).await?;
+        Ok(())
+    }
+
+    /**
+     * 签名后请求交易系统接口，token 失效时重试一次，错误码不为0时返回 DouyinError::Api
+     */
+    async fn trade_request<T>(&mut self, path: &str, body: Value) -> Result<T, DouyinError>
+        where T: DeserializeOwned + Default
+    {
+        let span = telemetry::CallSpan::call(path);
+        let open_base_url = self.open_base_url.clone();
+        let data = self.retry_on_token_error(span, Endpoint::Signed(&open_base_url), path, &body, api::json::<ApiRes<TradeRes<T>>>).await?;
+        Ok(data.into_result()?.data.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::{tests::stub_server, CachedToken, MemoryTokenStore, TokenStore};
+
+    fn sku(price: u64, quantity: u32) -> TradeSku {
+        TradeSku { sku_id: "sku".into(), price: Fen(price), quantity, ..Default::default() }
+    }
+
+    #[test]
+    fn new_sums_sku_amounts() {
+        let order = CreateTradeOrder::new("o1", vec![sku(100, 3), sku(1, 1)], OrderEntrySchema::default()).unwrap();
+        assert_eq!(order.total_amount, Fen(301));
+        let order = CreateTradeOrder::new("o1", vec![], OrderEntrySchema::default()).unwrap();
+        assert_eq!(order.total_amount, Fen(0));
+    }
+
+    #[test]
+    fn new_rejects_overflowing_amounts() {
+        let res = CreateTradeOrder::new("o1", vec![sku(u64::MAX / 2 + 1, 2)], OrderEntrySchema::default());
+        assert!(matches!(res, Err(DouyinError::InvalidParam(_))));
+        let res = CreateTradeOrder::new("o1", vec![sku(u64::MAX, 1), sku(1, 1)], OrderEntrySchema::default());
+        assert!(matches!(res, Err(DouyinError::InvalidParam(_))));
+    }
+
+    #[tokio::test]
+    async fn query_order_sends_only_the_given_id() {
+        let (base_url, requests) = stub_server(vec![
+            r#"{"err_no":0,"data":{"order_id":"d1","out_order_no":"o1"}}"#,
+            r#"{"err_no":0,"data":{"order_id":"d1","out_order_no":"o1"}}"#,
+        ]).await;
+        let store = Arc::new(MemoryTokenStore::new());
+        store.save("appid", &CachedToken::new("appid", "token".into(), 7200)).await.unwrap();
+        let mut sdk = SDK::builder()
+            .appid("appid")
+            .secret("secret")
+            .app_private_key(include_str!("../examples/private_key.pem"))
+            .open_base_url(&base_url)
+            .token_store(store)
+            .build()
+            .unwrap();
+
+        sdk.query_trade_order(None, Some("o1")).await.unwrap();
+        sdk.query_trade_order(Some("d1"), None).await.unwrap();
+        assert!(matches!(sdk.query_trade_order(None, None).await, Err(DouyinError::InvalidParam(_))));
+
+        let requests = requests.lock().unwrap();
+        let body = |request: &str| serde_json::from_str::<Value>(request.split_once('\n').unwrap().1).unwrap();
+        assert_eq!(body(&requests[0]), json!({ "out_order_no": "o1" }));
+        assert_eq!(body(&requests[1]), json!({ "order_id": "d1" }));
+    }
+}
@@ -0,0 +1,238 @@
+/*
+* 上传视频并发布到授权用户的抖音账号，需要 video.create 权限
+* 小视频直接上传，大视频分片上传；分片上传的进度保存在 UploadSession 中，失败后用同一个 session 重新调用会跳过已上传的分片。
+*/
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::{oauth::UserPayload, DouyinError, SDK};
+
+/**
+ * 分片的最小大小，最后一个分片除外
+ */
+pub const VIDEO_PART_MIN_SIZE: u64 = 5 * 1024 * 1024;
+
+/**
+ * 默认的分片大小，upload_video_file 中小于等于这个大小（且不超过 VIDEO_UPLOAD_MAX_SIZE）的视频直接上传
+ */
+pub const DEFAULT_VIDEO_PART_SIZE: u64 = 20 * 1024 * 1024;
+
+/**
+ * 直接上传的最大字节数，直接上传时整个视频会读入内存，更大的视频需要分片上传
+ */
+pub const VIDEO_UPLOAD_MAX_SIZE: u64 = 50 * 1024 * 1024;
+
+/**
+ * 上传进度
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct UploadProgress {
+    pub uploaded: u64, // 已上传的字节数，包含续传时跳过的分片
+    pub total: u64, // 视频总字节数
+    pub part_number: Option<u32>, // 刚上传完成的分片序号，直接上传时为 None
+}
+
+/**
+ * 上传进度回调
+ */
+pub type UploadProgressFn = dyn Fn(UploadProgress) + Send + Sync;
+
+/**
+ * 上传后的视频，video_id 用于 create_video
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct UploadedVideo {
+    pub video_id: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/**
+ * 分片上传的状态，可以序列化保存，进程重启后继续上传
+ */
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct UploadSession {
+    pub upload_id: String,
+    pub part_size: u64, // 分片大小，续传时必须与第一次上传一致
+    pub completed_parts: Vec<u32>, // 已上传的分片序号，从1开始
+}
+
+/**
+ * 发布视频的参数，可选字段直接赋值
+ */
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct CreateVideo {
+    pub video_id: String, // 上传后的 video_id
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>, // 视频标题，可以带话题和 @用户
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cover_tsp: Option<f64>, // 使用视频第几秒的画面作为封面
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub micro_app_id: Option<String>, // 视频挂载的小程序 appid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub micro_app_title: Option<String>, // 小程序标题
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub micro_app_url: Option<String>, // 小程序页面路径
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub poi_id: Option<String>, // 地理位置 id
+}
+
+impl CreateVideo {
+    pub fn new(video_id: &str) -> Self {
+        CreateVideo {
+            video_id: video_id.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+struct UploadVideoRes {
+    video: UploadedVideo,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+struct PartInitRes {
+    upload_id: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+struct CreateVideoRes {
+    item_id: String,
+}
+
+impl SDK {
+    /**
+     * 直接上传整个视频，适合较小的视频
+     * 视频会全部读入内存，超过 VIDEO_UPLOAD_MAX_SIZE 时返回 DouyinError::InvalidParam，请改用分片上传
+     */
+    pub async fn upload_video<R>(&self, open_id: &str, reader: R, file_name: &str, progress: Option<&UploadProgressFn>) -> Result<UploadedVideo, DouyinError>
+        where R: AsyncRead + Unpin
+    {
+        let mut data = Vec::new();
+        // 多读一个字节用来判断是否超过上限，不会把超大的视频全部读入内存
+        reader.take(VIDEO_UPLOAD_MAX_SIZE + 1).read_to_end(&mut data).await?;
+        if data.len() as u64 > VIDEO_UPLOAD_MAX_SIZE {
+            return Err(DouyinError::InvalidParam(format!("直接上传的视频不能超过{}字节，请使用分片上传", VIDEO_UPLOAD_MAX_SIZE)));
+        }
+        let total = data.len() as u64;
+        let res: UploadVideoRes = self.user_request(open_id, "/video/upload/", &[], UserPayload::Video { data: &data, file_name }).await?;
+        if let Some(progress) = progress {
+            progress(UploadProgress { uploaded: total, total, part_number: None });
+        }
+        Ok(res.video)
+    }
+
+    /**
+     * 初始化分片上传
+     */
+    pub async fn init_video_part_upload(&self, open_id: &str, part_size: u64) -> Result<UploadSession, DouyinError> {
+        if part_size < VIDEO_PART_MIN_SIZE {
+            return Err(DouyinError::InvalidParam(format!("分片大小不能小于{}字节", VIDEO_PART_MIN_SIZE)));
+        }
+        let res: PartInitRes = self.user_request(open_id, "/video/part/init/", &[], UserPayload::Post).await?;
+        Ok(UploadSession {
+            upload_id: res.upload_id,
+            part_size,
+            completed_parts: Vec::new(),
+        })
+    }
+
+    /**
+     * 从头读取视频并依次上传分片，session 中已完成的分片只读取不上传
+     * 每个分片上传成功后更新 session，失败时可以保存 session，之后用新的 reader 重新调用
+     */
+    pub async fn upload_video_parts<R>(&self, open_id: &str, session: &mut UploadSession, mut reader: R, total: u64, progress: Option<&UploadProgressFn>) -> Result<(), DouyinError>
+        where R: AsyncRead + Unpin
+    {
+        let mut uploaded = 0;
+        let mut part_number = 0;
+        loop {
+            part_number += 1;
+            let mut data = Vec::new();
+            (&mut reader).take(session.part_size).read_to_end(&mut data).await?;
+            if data.is_empty() {
+                break;
+            }
+            let size = data.len() as u64;
+            if !session.completed_parts.contains(&part_number) {
+                let upload_id = session.upload_id.clone();
+                let part = part_number.to_string();
+                let _: serde_json::Value = self.user_request(open_id, "/video/part/upload/", &[
+                    ("upload_id", upload_id.as_str()),
+                    ("part_number", part.as_str()),
+                ], UserPayload::Video { data: &data, file_name: "video.mp4" }).await?;
+                session.completed_parts.push(part_number);
+            }
+            uploaded += size;
+            if let Some(progress) = progress {
+                progress(UploadProgress { uploaded, total, part_number: Some(part_number) });
+            }
+            if size < session.part_size {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * 所有分片上传完成后合并
+     */
+    pub async fn complete_video_part_upload(&self, open_id: &str, session: &UploadSession) -> Result<UploadedVideo, DouyinError> {
+        let res: UploadVideoRes = self.user_request(open_id, "/video/part/complete/", &[
+            ("upload_id", session.upload_id.as_str()),
+        ], UserPayload::Post).await?;
+        Ok(res.video)
+    }
+
+    /**
+     * 上传本地视频文件，小于等于 part_size 且不超过 VIDEO_UPLOAD_MAX_SIZE 时直接上传，否则分片上传
+     * session 为 Some 时继续之前的分片上传；失败时 session 中保存着上传进度，成功后清空
+     */
+    pub async fn upload_video_file(&self, open_id: &str, path: impl AsRef<Path>, part_size: u64, session: &mut Option<UploadSession>, progress: Option<&UploadProgressFn>) -> Result<UploadedVideo, DouyinError> {
+        let path = path.as_ref();
+        let file = tokio::fs::File::open(path).await?;
+        let total = file.metadata().await?.len();
+        if session.is_none() && total <= part_size.min(VIDEO_UPLOAD_MAX_SIZE) {
+            let file_name = path.file_name().map_or("video.mp4".into(), |name| name.to_string_lossy());
+            return self.upload_video(open_id, file, &file_name, progress).await;
+        }
+        let current = match session {
+            Some(current) => current,
+            None => session.insert(self.init_video_part_upload(open_id, part_size).await?),
+        };
+        self.upload_video_parts(open_id, current, file, total, progress).await?;
+        let video = self.complete_video_part_upload(open_id, current).await?;
+        *session = None;
+        Ok(video)
+    }
+
+    /**
+     * 发布视频，返回视频的 item_id
+     */
+    pub async fn create_video(&self, open_id: &str, video: &CreateVideo) -> Result<String, DouyinError> {
+        let body = serde_json::to_value(video)?;
+        let res: CreateVideoRes = self.user_request(open_id, "/video/create/", &[], UserPayload::Json(&body)).await?;
+        Ok(res.item_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DouyinConfig;
+
+    #[tokio::test]
+    async fn upload_video_rejects_oversized_reader() {
+        let sdk = SDK::new(DouyinConfig { appid: "appid", secret: "secret", ..Default::default() });
+        let reader = tokio::io::repeat(0).take(VIDEO_UPLOAD_MAX_SIZE + 1);
+        let res = sdk.upload_video("open_id", reader, "video.mp4", None).await;
+        assert!(matches!(res, Err(DouyinError::InvalidParam(_))));
+    }
+}
@@ -0,0 +1,114 @@
+/*
+* 授权用户的信息和粉丝数据，需要用户先通过 oauth 授权，接口使用 UserTokenStore 中的 token
+*/
+use serde::{Deserialize, Serialize};
+
+use crate::{oauth::UserPayload, DouyinError, Page, Paginator, SDK};
+
+/**
+ * 抖音用户的公开信息，需要 user_info 权限（与小程序解密得到的 crypto::UserInfo 不同）
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct OpenUserInfo {
+    pub open_id: String, // 用户在当前应用的 ID
+    pub union_id: String, // 用户在同一开发者账号下的唯一 ID
+    pub nickname: String, // 昵称
+    pub avatar: String, // 头像
+    pub avatar_larger: String, // 大图头像
+    pub e_account_role: String, // 企业号类型，普通用户为空
+}
+
+/**
+ * 每天的粉丝数，需要 data.external.user 权限
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct FansStat {
+    pub date: String, // 日期，例如 2023-01-01
+    pub new_fans: i64, // 当天新增粉丝数，可能为负数
+    pub total_fans: u64, // 当天的粉丝总数
+}
+
+/**
+ * 粉丝信息
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Fan {
+    pub open_id: String,
+    pub union_id: String,
+    pub nickname: String,
+    pub avatar: String,
+    pub gender: i64, // 0 未知 1 男 2 女
+    pub country: String,
+    pub province: String,
+    pub city: String,
+}
+
+/**
+ * 一页粉丝列表
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct FansPage {
+    pub list: Vec<Fan>,
+    pub cursor: i64, // 下一页的游标
+    pub has_more: bool, // 是否还有下一页
+}
+
+impl From<FansPage> for Page<Fan> {
+    fn from(page: FansPage) -> Self {
+        Page { items: page.list, cursor: page.cursor, has_more: page.has_more }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+struct FansDataRes {
+    result_list: Vec<FansStat>,
+}
+
+impl SDK {
+    /**
+     * 获取用户公开信息
+     */
+    pub async fn user_info(&self, open_id: &str) -> Result<OpenUserInfo, DouyinError> {
+        self.user_request(open_id, "/oauth/userinfo/", &[], UserPayload::TokenForm).await
+    }
+
+    /**
+     * 获取最近几天每天的粉丝数，date_type 可选 7、15、30
+     */
+    pub async fn fans_data(&self, open_id: &str, date_type: u32) -> Result<Vec<FansStat>, DouyinError> {
+        let date_type = date_type.to_string();
+        let data: FansDataRes = self.user_request(open_id, "/data/external/user/fans/", &[("date_type", date_type.as_str())], UserPayload::Get).await?;
+        Ok(data.result_list)
+    }
+
+    /**
+     * 获取粉丝总数，取最近一天的数据
+     */
+    pub async fn fans_count(&self, open_id: &str) -> Result<u64, DouyinError> {
+        let list = self.fans_data(open_id, 7).await?;
+        Ok(list.iter().max_by(|a, b| a.date.cmp(&b.date)).map_or(0, |stat| stat.total_fans))
+    }
+
+    /**
+     * 分页获取粉丝列表，需要 fans.list 权限，第一页 cursor 传0，count 最大为20
+     */
+    pub async fn fans_list(&self, open_id: &str, cursor: i64, count: u32) -> Result<FansPage, DouyinError> {
+        let cursor = cursor.to_string();
+        let count = count.to_string();
+        self.user_request(open_id, "/fans/list/", &[("cursor", cursor.as_str()), ("count", count.as_str())], UserPayload::Get).await
+    }
+
+    /**
+     * 按游标自动翻页，逐个返回粉丝
+     */
+    pub fn fans<'a>(&'a self, open_id: &'a str) -> Paginator<'a, Fan> {
+        Paginator::new(move |cursor, count| async move {
+            Ok(self.fans_list(open_id, cursor, count).await?.into())
+        })
+    }
+}
@@ -0,0 +1,132 @@
+/*
+* 授权用户的视频列表和视频数据，需要用户先通过 oauth 授权
+*/
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{oauth::UserPayload, DouyinError, Page, Paginator, SDK};
+
+/**
+ * video_data 每次请求最多查询的视频数量，超过时会分批请求
+ */
+pub const VIDEO_DATA_BATCH_SIZE: usize = 20;
+
+/**
+ * 视频的统计数据
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct VideoStatistics {
+    pub play_count: u64, // 播放数
+    pub digg_count: u64, // 点赞数
+    pub comment_count: u64, // 评论数
+    pub share_count: u64, // 分享数
+    pub forward_count: u64, // 转发数
+    pub download_count: u64, // 下载数
+}
+
+/**
+ * 视频信息
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Video {
+    pub item_id: String, // 视频 id
+    pub title: String, // 视频标题
+    pub cover: String, // 视频封面
+    pub share_url: String, // 视频播放页面
+    pub create_time: u64, // 发布时间戳（秒）
+    pub is_top: bool, // 是否置顶
+    pub is_reviewed: bool, // 是否审核通过
+    pub video_status: i64, // 1 已发布 2 不适宜公开 4 审核中
+    pub statistics: VideoStatistics,
+}
+
+/**
+ * 一页视频列表
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct VideoPage {
+    pub list: Vec<Video>,
+    pub cursor: i64, // 下一页的游标
+    pub has_more: bool, // 是否还有下一页
+}
+
+/**
+ * 视频每天的数据，需要 data.external.item 权限
+ */
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct VideoDailyStat {
+    pub date: String, // 日期，例如 2023-01-01
+    pub total_play: u64, // 播放数
+    pub total_like: u64, // 点赞数
+    pub total_comment: u64, // 评论数
+    pub total_share: u64, // 分享数
+    pub avg_play_duration: f64, // 平均播放时长（秒）
+}
+
+impl From<VideoPage> for Page<Video> {
+    fn from(page: VideoPage) -> Self {
+        Page { items: page.list, cursor: page.cursor, has_more: page.has_more }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+struct VideoDataRes {
+    list: Vec<Video>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+struct VideoStatRes {
+    result_list: Vec<VideoDailyStat>,
+}
+
+impl SDK {
+    /**
+     * 分页获取用户的视频列表，需要 video.list 权限，第一页 cursor 传0，count 最大为20
+     */
+    pub async fn video_list(&self, open_id: &str, cursor: i64, count: u32) -> Result<VideoPage, DouyinError> {
+        let cursor = cursor.to_string();
+        let count = count.to_string();
+        self.user_request(open_id, "/video/list/", &[("cursor", cursor.as_str()), ("count", count.as_str())], UserPayload::Get).await
+    }
+
+    /**
+     * 按游标自动翻页，逐个返回用户的视频
+     */
+    pub fn videos<'a>(&'a self, open_id: &'a str) -> Paginator<'a, Video> {
+        Paginator::new(move |cursor, count| async move {
+            Ok(self.video_list(open_id, cursor, count).await?.into())
+        })
+    }
+
+    /**
+     * 按 item_id 查询视频信息和统计数据，需要 video.data 权限，超过 VIDEO_DATA_BATCH_SIZE 个时分批请求
+     */
+    pub async fn video_data<S: AsRef<str>>(&self, open_id: &str, item_ids: &[S]) -> Result<Vec<Video>, DouyinError> {
+        let mut list = Vec::with_capacity(item_ids.len());
+        for chunk in item_ids.chunks(VIDEO_DATA_BATCH_SIZE) {
+            let item_ids: Vec<&str> = chunk.iter().map(|item_id| item_id.as_ref()).collect();
+            let body = json!({ "item_ids": item_ids });
+            let data: VideoDataRes = self.user_request(open_id, "/video/data/", &[], UserPayload::Json(&body)).await?;
+            list.extend(data.list);
+        }
+        Ok(list)
+    }
+
+    /**
+     * 获取视频最近几天每天的数据，date_type 可选 7、15、30
+     */
+    pub async fn video_stats(&self, open_id: &str, item_id: &str, date_type: u32) -> Result<Vec<VideoDailyStat>, DouyinError> {
+        let date_type = date_type.to_string();
+        let data: VideoStatRes = self.user_request(open_id, "/data/external/item/base/", &[
+            ("item_id", item_id),
+            ("date_type", date_type.as_str()),
+        ], UserPayload::Get).await?;
+        Ok(data.result_list)
+    }
+}